@@ -0,0 +1,599 @@
+//! `tasks`, a non-interactive frontend for scripting against task files.
+//!
+//! Commands that take a `<selector>` accept a task id as printed by `tasks
+//! list` (counting from 1), an exact task name, or a query such as
+//! `"tag:work status:open report"`, see `tasks_core::query::Query`. `done`,
+//! `undone` and `rm` act on every match, `edit` and `mv` need exactly one.
+
+use std::{fmt::Display, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand};
+use serde_json::{json, Value};
+use tasks_core::{
+    clock, paths,
+    query::Query,
+    store::{FileStore, TaskStore},
+    tasks::{Priority, Repeat, TaskError, TaskItem, TaskList},
+    urgency::UrgencyConfig,
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "tasks", about = "Manage a .tl task list from the command line")]
+struct Cli {
+    /// the task file to work on, defaults to $XDG_DATA_HOME/tasks/tasks.tl
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+    /// print json instead of text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// add a task to the end of the list
+    Add {
+        name: String,
+        #[command(flatten)]
+        fields: Fields,
+    },
+    /// list tasks, deferred tasks are left out unless asked for
+    List {
+        /// only tasks matching this query
+        query: Option<String>,
+        /// include deferred tasks
+        #[arg(long)]
+        all: bool,
+        /// only deferred tasks
+        #[arg(long, conflicts_with = "all")]
+        waiting: bool,
+        /// most urgent first instead of file order
+        #[arg(long)]
+        urgency: bool,
+    },
+    /// mark tasks as done
+    Done { selector: String },
+    /// mark tasks as not done
+    Undone { selector: String },
+    /// remove tasks
+    Rm { selector: String },
+    /// change the fields of a task
+    Edit {
+        selector: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        fields: Fields,
+    },
+    /// print every field of the selected tasks
+    Show { selector: String },
+    /// move a task so it gets the id `to`
+    Mv { selector: String, to: usize },
+}
+
+impl Command {
+    /// commands that only read the list don't take the lock
+    fn writes(&self) -> bool {
+        !matches!(self, Command::List { .. } | Command::Show { .. })
+    }
+}
+
+/// fields shared by `add` and `edit`, `none` clears an optional field
+#[derive(Debug, Default, Args)]
+struct Fields {
+    #[arg(long)]
+    data: Option<String>,
+    /// YYYY-MM-DD
+    #[arg(long)]
+    due: Option<String>,
+    /// low, medium or high
+    #[arg(long)]
+    priority: Option<String>,
+    /// comma separated
+    #[arg(long)]
+    tags: Option<String>,
+    #[arg(long)]
+    blocked: Option<bool>,
+    /// expected effort like 1h30m, a bare number is minutes
+    #[arg(long)]
+    estimate: Option<String>,
+    /// hide the task until this YYYY-MM-DD date
+    #[arg(long)]
+    wait: Option<String>,
+    /// the YYYY-MM-DD day the task is planned for
+    #[arg(long)]
+    planned: Option<String>,
+    /// daily or weekly, a finished task opens again the next day or week
+    #[arg(long)]
+    repeat: Option<String>,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Task(TaskError),
+    NoMatch(String),
+    Ambiguous(String, usize),
+    Invalid(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Task(err) => write!(f, "{err}"),
+            CliError::NoMatch(selector) => write!(f, "no task matches {selector:?}"),
+            CliError::Ambiguous(selector, count) => write!(
+                f,
+                "{selector:?} matches {count} tasks, use an id to pick one"
+            ),
+            CliError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<TaskError> for CliError {
+    fn from(err: TaskError) -> Self {
+        CliError::Task(err)
+    }
+}
+
+/// parses an optional field argument, `none` clears the field
+fn optional<T>(
+    value: &str,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, CliError> {
+    if value == "none" {
+        return Ok(None);
+    }
+    parse(value)
+        .map(Some)
+        .ok_or_else(|| CliError::Invalid(format!("expected {expected}, found {value}")))
+}
+
+fn date(value: &str) -> Result<Option<i64>, CliError> {
+    optional(value, "a YYYY-MM-DD date", clock::parse_date)
+}
+
+impl Fields {
+    fn apply(&self, item: &mut TaskItem) -> Result<(), CliError> {
+        if let Some(data) = &self.data {
+            item.set_data(data.clone());
+        }
+        if let Some(due) = &self.due {
+            item.set_due(date(due)?);
+        }
+        if let Some(priority) = &self.priority {
+            let priority = optional(priority, "low, medium or high", |p| Priority::parse(p).ok())?;
+            item.set_priority(priority);
+        }
+        if let Some(tags) = &self.tags {
+            let tags = tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty() && *tag != "none")
+                .map(str::to_string)
+                .collect();
+            item.set_tags(tags);
+        }
+        if let Some(blocked) = self.blocked {
+            item.set_blocked(blocked);
+        }
+        if let Some(estimate) = &self.estimate {
+            item.set_estimate(optional(
+                estimate,
+                "a duration like 1h30m",
+                clock::parse_duration,
+            )?);
+        }
+        if let Some(wait) = &self.wait {
+            item.set_wait(date(wait)?);
+        }
+        if let Some(planned) = &self.planned {
+            item.set_planned(date(planned)?);
+        }
+        if let Some(repeat) = &self.repeat {
+            item.set_repeat(optional(repeat, "daily or weekly", |r| {
+                Repeat::parse(r).ok()
+            })?);
+        }
+        Ok(())
+    }
+}
+
+/// indices of the tasks `selector` picks, see the module docs
+fn select(list: &TaskList, selector: &str) -> Result<Vec<usize>, CliError> {
+    if let Ok(id) = selector.parse::<usize>() {
+        return match id {
+            1.. if id <= list.len() => Ok(vec![id - 1]),
+            _ => Err(CliError::NoMatch(selector.to_string())),
+        };
+    }
+
+    let named: Vec<usize> = (0..list.len())
+        .filter(|index| list[*index].name() == selector)
+        .collect();
+    if !named.is_empty() {
+        return Ok(named);
+    }
+
+    let matched = Query::parse(selector)?.filter(list);
+    match matched.is_empty() {
+        true => Err(CliError::NoMatch(selector.to_string())),
+        false => Ok(matched),
+    }
+}
+
+fn select_one(list: &TaskList, selector: &str) -> Result<usize, CliError> {
+    match select(list, selector)?.as_slice() {
+        [index] => Ok(*index),
+        matched => Err(CliError::Ambiguous(selector.to_string(), matched.len())),
+    }
+}
+
+fn task_json(list: &TaskList, index: usize) -> Value {
+    let item = &list[index];
+    json!({
+        "id": index + 1,
+        "name": item.name(),
+        "status": item.status(),
+        "data": item.data(),
+        "created": item.created_at(),
+        "completed": item.completed_at(),
+        "due": item.due().map(clock::format_date),
+        "priority": item.priority().map(|p| p.to_string()),
+        "tags": item.tags(),
+        "blocked": item.blocked(),
+        "wait": item.wait().map(clock::format_date),
+        "planned": item.planned().map(clock::format_date),
+        "estimate": item.estimate(),
+        "tracked": item.tracked(clock::now()),
+        "pomodoros": item.pomodoros(),
+        "repeat": item.repeat().map(|r| r.to_string()),
+        "urgency": UrgencyConfig::default().urgency(item, clock::now()),
+    })
+}
+
+/// one line per task, `  3 [x] name due 2024-03-01 !high #work`
+fn task_line(list: &TaskList, index: usize) -> String {
+    let item = &list[index];
+    let mut line = format!(
+        "{:>3} [{}] {}",
+        index + 1,
+        if item.status() { 'x' } else { ' ' },
+        item.name()
+    );
+    if let Some(due) = item.due() {
+        line.push_str(&format!(" due {}", clock::format_date(due)));
+    }
+    if let Some(priority) = item.priority() {
+        line.push_str(&format!(" !{priority}"));
+    }
+    for tag in item.tags() {
+        line.push_str(&format!(" #{tag}"));
+    }
+    if item.blocked() {
+        line.push_str(" (blocked)");
+    }
+    if let Some(wait) = item.wait().filter(|_| item.is_waiting(clock::today())) {
+        line.push_str(&format!(" (waiting until {})", clock::format_date(wait)));
+    }
+    line
+}
+
+fn task_details(list: &TaskList, index: usize) -> String {
+    let item = &list[index];
+    let mut details = task_line(list, index);
+    if !item.data().is_empty() {
+        details.push_str(&format!("\n    {}", item.data().replace('\n', "\n    ")));
+    }
+    let mut field = |key: &str, value: String| details.push_str(&format!("\n    {key}: {value}"));
+    if let Some(repeat) = item.repeat() {
+        field("repeat", repeat.to_string());
+    }
+    if let Some(planned) = item.planned() {
+        field("planned", clock::format_date(planned));
+    }
+    if let Some(estimate) = item.estimate() {
+        field("estimate", clock::format_duration(estimate));
+    }
+    let tracked = item.tracked(clock::now());
+    if tracked > 0 {
+        field("tracked", clock::format_duration(tracked));
+    }
+    if item.pomodoros() > 0 {
+        field("pomodoros", item.pomodoros().to_string());
+    }
+    field(
+        "urgency",
+        format!(
+            "{:.1}",
+            UrgencyConfig::default().urgency(item, clock::now())
+        ),
+    );
+    details
+}
+
+fn print(list: &TaskList, indices: &[usize], json: bool, line: fn(&TaskList, usize) -> String) {
+    if json {
+        let tasks: Vec<Value> = indices.iter().map(|i| task_json(list, *i)).collect();
+        println!("{}", Value::Array(tasks));
+        return;
+    }
+    for index in indices {
+        println!("{}", line(list, *index));
+    }
+}
+
+fn set_status(list: &mut TaskList, selector: &str, status: bool) -> Result<Vec<usize>, CliError> {
+    let selected = select(list, selector)?;
+    for index in &selected {
+        let mut item = list[*index].clone();
+        item.set_status(status);
+        list.replace(*index, item);
+    }
+    Ok(selected)
+}
+
+/// names are written on a single line, the file format escapes everything
+/// else
+fn task_name(name: &str) -> Result<String, CliError> {
+    if name.trim().is_empty() {
+        return Err(CliError::Invalid("a task needs a name".to_string()));
+    }
+    if name.contains(['\n', '\r']) {
+        return Err(CliError::Invalid(
+            "a task name can't span several lines".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn add(list: &mut TaskList, name: &str, fields: &Fields) -> Result<usize, CliError> {
+    let mut item = TaskItem::new(task_name(name)?, String::new(), false);
+    fields.apply(&mut item)?;
+    list.push(item);
+    Ok(list.len() - 1)
+}
+
+fn edit(
+    list: &mut TaskList,
+    selector: &str,
+    name: Option<&str>,
+    fields: &Fields,
+) -> Result<usize, CliError> {
+    let index = select_one(list, selector)?;
+    let mut item = list[index].clone();
+    if let Some(name) = name {
+        item.set_name(task_name(name)?);
+    }
+    fields.apply(&mut item)?;
+    list.replace(index, item);
+    Ok(index)
+}
+
+/// moves the selected task so it gets the id `to`, returns its new index
+fn move_task(list: &mut TaskList, selector: &str, to: usize) -> Result<usize, CliError> {
+    let from = select_one(list, selector)?;
+    if !(1..=list.len()).contains(&to) {
+        return Err(CliError::Invalid(format!(
+            "there is no id {to}, the list has {} tasks",
+            list.len()
+        )));
+    }
+    list.move_item(from, to - 1);
+    Ok(to - 1)
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    let file = cli
+        .file
+        .or_else(paths::default_task_file)
+        .ok_or_else(|| CliError::Invalid("no task file given and $HOME is not set".to_string()))?;
+    let mut store = FileStore::new(file);
+    // adding the first task creates the file
+    if let Command::Add { .. } = cli.command {
+        store.create_if_missing()?;
+    }
+    // refuse to write behind the back of a TUI that has the file open
+    if cli.command.writes() {
+        store.lock()?;
+    }
+    let mut list = store.load()?;
+    // habits finished in an earlier day or week are open again, this is
+    // written back by the next command that saves
+    list.roll_over(clock::today());
+
+    let changed = match &cli.command {
+        Command::Add { name, fields } => vec![add(&mut list, name, fields)?],
+        Command::List {
+            query,
+            all,
+            waiting,
+            urgency,
+        } => {
+            let query = Query::parse(query.as_deref().unwrap_or_default())?;
+            let today = clock::today();
+            let order = match urgency {
+                true => UrgencyConfig::default().sort(&list, clock::now()),
+                false => (0..list.len()).collect(),
+            };
+            let listed: Vec<usize> = order
+                .into_iter()
+                .filter(|index| {
+                    let item = &list[*index];
+                    let shown = *all || item.is_waiting(today) == *waiting;
+                    shown && query.matches(item)
+                })
+                .collect();
+            print(&list, &listed, cli.json, task_line);
+            return Ok(());
+        }
+        Command::Show { selector } => {
+            let selected = select(&list, selector)?;
+            print(&list, &selected, cli.json, task_details);
+            return Ok(());
+        }
+        Command::Done { selector } => set_status(&mut list, selector, true)?,
+        Command::Undone { selector } => set_status(&mut list, selector, false)?,
+        Command::Rm { selector } => {
+            let selected = select(&list, selector)?;
+            // printed before removing so the ids still line up
+            print(&list, &selected, cli.json, task_line);
+            for index in selected.iter().rev() {
+                list.remove(*index);
+            }
+            store.save(&list)?;
+            return Ok(());
+        }
+        Command::Edit {
+            selector,
+            name,
+            fields,
+        } => vec![edit(&mut list, selector, name.as_deref(), fields)?],
+        Command::Mv { selector, to } => vec![move_task(&mut list, selector, *to)?],
+    };
+
+    store.save(&list)?;
+    print(&list, &changed, cli.json, task_line);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("tasks: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use tasks_core::{
+        store::{FileStore, TaskStore},
+        tasks::{TaskItem, TaskList},
+    };
+
+    use super::{add, edit, move_task, run, select, select_one, Cli, CliError, Fields};
+
+    fn list(names: &[&str]) -> TaskList {
+        let mut list = TaskList::new();
+        for name in names {
+            list.push(TaskItem::new(name.to_string(), String::new(), false));
+        }
+        list
+    }
+
+    fn names(list: &TaskList) -> Vec<String> {
+        list.iter().map(|item| item.name().to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_select() {
+        let mut list = list(&["a", "b", "a", "3"]);
+        let mut item = list[1].clone();
+        item.set_tags(vec!["work".into()]);
+        list.replace(1, item);
+
+        // ids count from 1 and win over a task named like a number
+        assert_eq!(select(&list, "1").unwrap(), [0]);
+        assert_eq!(select(&list, "3").unwrap(), [2]);
+        assert!(matches!(select(&list, "0"), Err(CliError::NoMatch(_))));
+        assert!(matches!(select(&list, "5"), Err(CliError::NoMatch(_))));
+        assert_eq!(select(&list, "a").unwrap(), [0, 2]);
+        assert_eq!(select(&list, "tag:work").unwrap(), [1]);
+        assert!(matches!(select(&list, "c"), Err(CliError::NoMatch(_))));
+
+        assert_eq!(select_one(&list, "b").unwrap(), 1);
+        assert!(matches!(
+            select_one(&list, "a"),
+            Err(CliError::Ambiguous(_, 2))
+        ));
+    }
+
+    #[test]
+    pub fn test_edit() {
+        let mut list = list(&["a", "b"]);
+        let fields = Fields {
+            data: Some("notes".into()),
+            due: Some("2024-03-01".into()),
+            priority: Some("h".into()),
+            ..Default::default()
+        };
+        assert_eq!(edit(&mut list, "b", Some("c"), &fields).unwrap(), 1);
+        assert_eq!(names(&list), ["a", "c"]);
+        assert_eq!(list[1].data(), "notes");
+        assert!(list[1].due().is_some());
+        assert!(list[1].priority().is_some());
+
+        // `none` clears a field, fields that aren't given stay as they are
+        let fields = Fields {
+            due: Some("none".into()),
+            ..Default::default()
+        };
+        edit(&mut list, "2", None, &fields).unwrap();
+        assert!(list[1].due().is_none());
+        assert_eq!(list[1].data(), "notes");
+
+        let fields = Fields {
+            due: Some("soon".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            edit(&mut list, "c", None, &fields),
+            Err(CliError::Invalid(_))
+        ));
+        assert!(edit(&mut list, "c", Some(" "), &Fields::default()).is_err());
+        assert!(edit(&mut list, "c", Some("x\ny"), &Fields::default()).is_err());
+        assert_eq!(names(&list), ["a", "c"]);
+    }
+
+    #[test]
+    pub fn test_move_task() {
+        let mut list = list(&["a", "b", "c"]);
+        assert_eq!(move_task(&mut list, "c", 1).unwrap(), 0);
+        assert_eq!(names(&list), ["c", "a", "b"]);
+        assert_eq!(move_task(&mut list, "1", 3).unwrap(), 2);
+        assert_eq!(names(&list), ["a", "b", "c"]);
+        assert!(matches!(
+            move_task(&mut list, "a", 4),
+            Err(CliError::Invalid(_))
+        ));
+        assert!(matches!(
+            move_task(&mut list, "a", 0),
+            Err(CliError::Invalid(_))
+        ));
+        assert_eq!(names(&list), ["a", "b", "c"]);
+    }
+
+    #[test]
+    pub fn test_special_characters_round_trip() {
+        let mut list = TaskList::new();
+        let fields = Fields {
+            data: Some("say \"hi\" there, see [link]".into()),
+            ..Default::default()
+        };
+        add(&mut list, "x]y", &fields).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("tasks_cli_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.tl");
+        let mut store = FileStore::new(&path);
+        store.overwrite(&list).unwrap();
+
+        // the next command can still read the file
+        let file = path.to_str().unwrap();
+        run(Cli::try_parse_from(["tasks", "-f", file, "add", "[next]"]).unwrap()).unwrap();
+        run(Cli::try_parse_from(["tasks", "-f", file, "edit", "x]y", "--data", "\\\""]).unwrap())
+            .unwrap();
+
+        let list = store.load().unwrap();
+        assert_eq!(names(&list), ["x]y", "[next]"]);
+        assert_eq!(list[0].data(), "\\\"");
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,50 @@
+//! A git merge driver for `.tl` files.
+//!
+//! ```text
+//! # .gitattributes
+//! *.tl merge=tasks
+//!
+//! git config merge.tasks.name "tasks .tl merge"
+//! git config merge.tasks.driver "tasks_merge %O %A %B"
+//! ```
+//!
+//! The merged list is written over `%A`. Field level clashes are wrapped in
+//! conflict markers and the driver exits with 1 so git reports the conflict.
+
+use std::process::ExitCode;
+
+use tasks_core::{
+    merge::merge,
+    store::{FileStore, TaskStore},
+};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [base, ours, theirs] = args.as_slice() else {
+        eprintln!("usage: tasks_merge <base> <ours> <theirs>");
+        return ExitCode::from(2);
+    };
+
+    let mut lists = vec![];
+    for path in [base, ours, theirs] {
+        match FileStore::new(path).load() {
+            Ok(list) => lists.push(list),
+            Err(err) => {
+                eprintln!("tasks_merge: {path}: {err}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let merged = merge(&lists[0], &lists[1], &lists[2]);
+    if let Err(err) = std::fs::write(ours, &merged.text) {
+        eprintln!("tasks_merge: failed to write {ours}: {err}");
+        return ExitCode::from(2);
+    }
+
+    if merged.is_clean() {
+        return ExitCode::SUCCESS;
+    }
+    eprintln!("tasks_merge: {} conflicts in {ours}", merged.conflicts);
+    ExitCode::FAILURE
+}
@@ -0,0 +1,134 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// seconds since the unix epoch, this is what every timestamp in a `.tl` file
+/// is stored as
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+pub const DAY: u64 = 60 * 60 * 24;
+
+/// the day `timestamp` falls on, counted from 1970-01-01. Days are UTC.
+pub fn day(timestamp: u64) -> i64 {
+    (timestamp / DAY) as i64
+}
+
+pub fn today() -> i64 {
+    day(now())
+}
+
+/// weeks start on monday, 1970-01-01 was a thursday
+pub fn week(day: i64) -> i64 {
+    (day + 3).div_euclid(7)
+}
+
+/// the monday of `week`
+pub fn week_start(week: i64) -> i64 {
+    week * 7 - 3
+}
+
+/// `h:mm:ss`
+pub fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// reads durations like `1h30m`, `45m` or `90s`, a bare number is minutes
+pub fn parse_duration(source: &str) -> Option<u64> {
+    let source = source.trim();
+    if let Ok(minutes) = source.parse::<u64>() {
+        return Some(minutes * 60);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for ch in source.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+        let unit = match ch {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        total += number.parse::<u64>().ok()? * unit;
+        number.clear();
+    }
+    (number.is_empty() && !source.is_empty()).then_some(total)
+}
+
+/// year, month and day of `day`, see
+/// <http://howardhinnant.github.io/date_algorithms.html>
+fn civil(day: i64) -> (i64, i64, i64) {
+    let z = day + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + (m <= 2) as i64, m, d)
+}
+
+fn from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// `YYYY-MM-DD`
+pub fn format_date(day: i64) -> String {
+    let (y, m, d) = civil(day);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// reads a `YYYY-MM-DD` date, `None` if it is malformed or doesn't exist
+pub fn parse_date(source: &str) -> Option<i64> {
+    let mut parts = source.trim().splitn(3, '-').map(|part| part.parse::<i64>());
+    let (y, m, d) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    let day = from_civil(y, m, d);
+    (civil(day) == (y, m, d)).then_some(day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_date, parse_date, parse_duration};
+
+    #[test]
+    pub fn test_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-02-29"), Some(19782));
+        assert_eq!(format_date(19782), "2024-02-29");
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("tomorrow"), None);
+    }
+
+    #[test]
+    pub fn test_durations() {
+        assert_eq!(parse_duration("45"), Some(45 * 60));
+        assert_eq!(parse_duration("1h30m"), Some(90 * 60));
+        assert_eq!(parse_duration("2h"), Some(2 * 3600));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("1h30"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
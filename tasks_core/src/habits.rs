@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    clock,
+    tasks::{Repeat, TaskItem},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HabitStats {
+    pub repeat: Repeat,
+    /// periods (days or weeks) in a row that ended with the task done, the
+    /// current period only breaks the streak once it is over
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    /// share of periods since the task was created that were completed
+    pub completion_rate: f64,
+    /// number of completions per day
+    pub days: BTreeMap<i64, usize>,
+}
+
+impl HabitStats {
+    /// `None` for tasks that don't repeat
+    pub fn new(item: &TaskItem, today: i64) -> Option<Self> {
+        let repeat = item.repeat()?;
+
+        let mut days = BTreeMap::new();
+        for at in item.completions() {
+            *days.entry(clock::day(at)).or_insert(0) += 1;
+        }
+
+        let mut periods: Vec<i64> = days.keys().map(|day| repeat.period(*day)).collect();
+        periods.dedup();
+
+        let mut longest_streak = 0;
+        let mut streak = 0;
+        let mut last = None;
+        for period in &periods {
+            streak = match last {
+                Some(last) if last + 1 == *period => streak + 1,
+                _ => 1,
+            };
+            longest_streak = longest_streak.max(streak);
+            last = Some(*period);
+        }
+
+        let current = repeat.period(today);
+        let current_streak = match last {
+            Some(last) if last >= current - 1 => streak,
+            _ => 0,
+        };
+
+        let first = item
+            .created_at()
+            .map(clock::day)
+            .into_iter()
+            .chain(days.keys().next().copied())
+            .min()
+            .map_or(current, |day| repeat.period(day));
+        let total = (current - first + 1).max(1) as f64;
+        let completion_rate = (periods.len() as f64 / total).min(1.0);
+
+        Some(Self {
+            repeat,
+            current_streak,
+            longest_streak,
+            completion_rate,
+            days,
+        })
+    }
+
+    pub fn completions_on(&self, day: i64) -> usize {
+        self.days.get(&day).copied().unwrap_or_default()
+    }
+
+    /// a grid of `weeks` columns ending with the current week and one row per
+    /// weekday starting on monday, days after `today` are `None`
+    pub fn heatmap(&self, today: i64, weeks: usize) -> Vec<Vec<Option<usize>>> {
+        let last_week = clock::week(today);
+        let first_week = last_week - weeks as i64 + 1;
+        (0..7)
+            .map(|weekday| {
+                (first_week..=last_week)
+                    .map(|week| clock::week_start(week) + weekday)
+                    .map(|day| (day <= today).then(|| self.completions_on(day)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HabitStats;
+    use crate::{
+        clock::DAY,
+        tasks::{Repeat, TaskList},
+    };
+
+    fn habit(repeat: &str, done: &[u64]) -> TaskList {
+        let mut source = "[habit]\nstatus = false\ndata = \"\"\ncreated = 0\n".to_string();
+        for day in done {
+            source.push_str(&format!("history = \"{} status true\"\n", day * DAY + 60));
+        }
+        source.push_str(&format!("repeat = \"{repeat}\"\n"));
+        TaskList::deserialize(source).unwrap()
+    }
+
+    #[test]
+    pub fn test_daily_streaks() {
+        let list = habit("daily", &[0, 1, 2, 5, 6, 6, 7]);
+        let stats = HabitStats::new(list.get(0).unwrap(), 8).unwrap();
+        assert_eq!(stats.repeat, Repeat::Daily);
+        assert_eq!(stats.longest_streak, 3);
+        // today isn't over yet so yesterday's streak still counts
+        assert_eq!(stats.current_streak, 3);
+        assert_eq!(stats.completions_on(6), 2);
+        assert!((stats.completion_rate - 6.0 / 9.0).abs() < f64::EPSILON);
+
+        let stats = HabitStats::new(list.get(0).unwrap(), 9).unwrap();
+        assert_eq!(stats.current_streak, 0);
+    }
+
+    #[test]
+    pub fn test_weekly_streaks_and_heatmap() {
+        // day 4 is the first monday after the epoch
+        let list = habit("weekly", &[4, 12, 13, 20]);
+        let stats = HabitStats::new(list.get(0).unwrap(), 22).unwrap();
+        assert_eq!(stats.longest_streak, 3);
+        assert_eq!(stats.current_streak, 3);
+
+        let heatmap = stats.heatmap(22, 2);
+        assert_eq!(heatmap.len(), 7);
+        // the columns start on mondays 11 and 18, today (22) is a friday
+        assert_eq!(heatmap[1], [Some(1), Some(0)]);
+        assert_eq!(heatmap[2], [Some(1), Some(1)]);
+        assert_eq!(heatmap[4], [Some(0), Some(0)]);
+        assert_eq!(heatmap[5], [Some(0), None]);
+    }
+
+    #[test]
+    pub fn test_roll_over() {
+        let mut list = habit("daily", &[]);
+        let mut item = list.get(0).unwrap().clone();
+        item.set_status(true);
+        list.replace(0, item);
+
+        assert!(list.roll_over(crate::clock::today()).is_empty());
+        assert_eq!(list.roll_over(crate::clock::today() + 1), [0]);
+        assert!(!list.get(0).unwrap().status());
+    }
+}
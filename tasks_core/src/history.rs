@@ -0,0 +1,204 @@
+use crate::tasks::{TaskItem, TaskList};
+
+/// A single change to a `TaskList`. Applying a command hands back the command
+/// that reverses it, which is what the undo and redo stacks are made of.
+#[derive(Debug, Clone)]
+pub enum TaskCommand {
+    Push(TaskItem),
+    Insert(usize, TaskItem),
+    Remove(usize),
+    /// replaces the task at the index, used for every field edit
+    Replace(usize, TaskItem),
+    Move {
+        from: usize,
+        to: usize,
+    },
+    /// several commands undone and redone as one step
+    Batch(Vec<TaskCommand>),
+}
+
+impl TaskCommand {
+    /// applies the command and returns its inverse, or `None` if the index
+    /// was out of bounds and nothing changed
+    pub fn apply(self, list: &mut TaskList) -> Option<TaskCommand> {
+        match self {
+            TaskCommand::Push(item) => {
+                list.push(item);
+                Some(TaskCommand::Remove(list.len() - 1))
+            }
+            TaskCommand::Insert(idx, item) => {
+                if idx > list.len() {
+                    return None;
+                }
+                list.set(idx, item);
+                Some(TaskCommand::Remove(idx))
+            }
+            TaskCommand::Remove(idx) => {
+                let item = list.get(idx)?.clone();
+                list.remove(idx);
+                Some(TaskCommand::Insert(idx, item))
+            }
+            TaskCommand::Replace(idx, item) => {
+                let old = list.replace(idx, item)?;
+                Some(TaskCommand::Replace(idx, old))
+            }
+            TaskCommand::Move { from, to } => {
+                if !list.move_item(from, to) {
+                    return None;
+                }
+                Some(TaskCommand::Move { from: to, to: from })
+            }
+            TaskCommand::Batch(commands) => {
+                let mut inverse = Vec::with_capacity(commands.len());
+                for command in commands {
+                    match command.apply(list) {
+                        Some(command) => inverse.push(command),
+                        None => {
+                            // roll back what was already applied
+                            inverse.into_iter().rev().for_each(|command| {
+                                command.apply(list);
+                            });
+                            return None;
+                        }
+                    }
+                }
+                inverse.reverse();
+                Some(TaskCommand::Batch(inverse))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<TaskCommand>,
+    redo: Vec<TaskCommand>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// applies `command` to `list` and records it, any redo history is dropped
+    pub fn apply(&mut self, list: &mut TaskList, command: TaskCommand) -> bool {
+        let Some(inverse) = command.apply(list) else {
+            return false;
+        };
+        self.undo.push(inverse);
+        self.redo.clear();
+        true
+    }
+
+    pub fn undo(&mut self, list: &mut TaskList) -> bool {
+        History::step(list, &mut self.undo, &mut self.redo)
+    }
+
+    pub fn redo(&mut self, list: &mut TaskList) -> bool {
+        History::step(list, &mut self.redo, &mut self.undo)
+    }
+
+    /// a command that can't be applied stays where it is, so it can be
+    /// retried once the list allows it again
+    fn step(list: &mut TaskList, from: &mut Vec<TaskCommand>, to: &mut Vec<TaskCommand>) -> bool {
+        let Some(command) = from.last().cloned() else {
+            return false;
+        };
+        match command.apply(list) {
+            Some(inverse) => {
+                from.pop();
+                to.push(inverse);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// forgets everything, used when the list is replaced wholesale
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{History, TaskCommand};
+    use crate::tasks::{TaskItem, TaskList};
+
+    fn names(list: &TaskList) -> Vec<String> {
+        list.iter().map(|t| t.name().to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_undo_redo() {
+        let mut list = TaskList::new();
+        let mut history = History::new();
+        let task = |name: &str| TaskItem::new(name.into(), "".into(), false);
+
+        history.apply(&mut list, TaskCommand::Push(task("a")));
+        history.apply(&mut list, TaskCommand::Push(task("b")));
+        history.apply(&mut list, TaskCommand::Replace(0, task("a2")));
+        history.apply(&mut list, TaskCommand::Move { from: 1, to: 0 });
+        assert_eq!(names(&list), ["b", "a2"]);
+        history.apply(&mut list, TaskCommand::Remove(0));
+        assert_eq!(names(&list), ["a2"]);
+
+        assert!(history.undo(&mut list));
+        assert_eq!(names(&list), ["b", "a2"]);
+        assert!(history.undo(&mut list));
+        assert_eq!(names(&list), ["a2", "b"]);
+        assert!(history.undo(&mut list));
+        assert_eq!(names(&list), ["a", "b"]);
+
+        assert!(history.redo(&mut list));
+        assert_eq!(names(&list), ["a2", "b"]);
+
+        // a new change drops what could have been redone
+        history.apply(&mut list, TaskCommand::Insert(0, task("c")));
+        assert!(!history.can_redo());
+        assert_eq!(names(&list), ["c", "a2", "b"]);
+
+        history.apply(
+            &mut list,
+            TaskCommand::Batch(vec![TaskCommand::Push(task("d")), TaskCommand::Remove(0)]),
+        );
+        assert_eq!(names(&list), ["a2", "b", "d"]);
+        assert!(history.undo(&mut list));
+        assert_eq!(names(&list), ["c", "a2", "b"]);
+        let failed = TaskCommand::Batch(vec![TaskCommand::Remove(0), TaskCommand::Remove(9)]);
+        assert!(!history.apply(&mut list, failed));
+        assert_eq!(names(&list), ["c", "a2", "b"]);
+
+        while history.undo(&mut list) {}
+        assert!(list.is_empty());
+        assert!(!history.apply(&mut list, TaskCommand::Remove(3)));
+    }
+
+    #[test]
+    pub fn test_failed_undo_is_kept() {
+        let mut list = TaskList::new();
+        let mut history = History::new();
+        history.apply(
+            &mut list,
+            TaskCommand::Push(TaskItem::new("a".into(), "".into(), false)),
+        );
+
+        // the list was replaced behind the history's back
+        let mut other = TaskList::new();
+        assert!(!history.undo(&mut other));
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+
+        assert!(history.undo(&mut list));
+        assert!(list.is_empty());
+    }
+}
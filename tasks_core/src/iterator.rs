@@ -15,7 +15,7 @@ impl Iterator for TaskIter<'_> {
     type Item = TaskItem;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx > self.list.list.len().saturating_sub(1) {
+        if self.idx > self.list.len().saturating_sub(1) {
             return None;
         }
 
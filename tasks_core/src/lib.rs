@@ -1,2 +1,14 @@
+pub mod clock;
+pub mod habits;
+pub mod history;
 pub mod iterator;
+pub mod merge;
+pub mod paths;
+pub mod planning;
+pub mod pomodoro;
+pub mod query;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
 pub mod tasks;
+pub mod urgency;
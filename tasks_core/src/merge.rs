@@ -0,0 +1,325 @@
+use std::fmt::Write;
+
+use crate::tasks::{TaskError, TaskItem, TaskList};
+
+/// A task broken up into its serialized `key = value` lines, so merging works
+/// on whatever fields the `.tl` format grows without knowing about them.
+/// `data` is kept as one value however many lines it spans.
+#[derive(Debug, Clone, PartialEq)]
+struct Fields {
+    /// the `[name]` header
+    header: String,
+    fields: Vec<(String, String)>,
+}
+
+fn text(write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>) -> String {
+    let mut buffer = vec![];
+    _ = write(&mut buffer);
+    String::from_utf8_lossy(&buffer)
+        .trim_end_matches('\n')
+        .to_string()
+}
+
+impl Fields {
+    fn new(item: &TaskItem) -> Self {
+        let mut fields: Vec<(String, String)> = vec![
+            ("status".to_string(), format!("status = {}", item.status())),
+            ("data".to_string(), text(|w| item.serialize_data(w))),
+        ];
+        // everything after `data` is written one line per value, repeated
+        // keys like `history` are grouped together
+        for line in text(|w| item.serialize_fields(w)).lines() {
+            let key = line
+                .split('=')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            match fields.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, value)) => {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+                None => fields.push((key, line.to_string())),
+            }
+        }
+
+        Self {
+            header: text(|w| item.serialize_name(w)),
+            fields,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|(key, _)| key.as_str())
+    }
+
+    fn write(&self, out: &mut String) {
+        _ = writeln!(out, "{}", self.header);
+        for (_, value) in &self.fields {
+            _ = writeln!(out, "{value}");
+        }
+    }
+}
+
+/// tasks are matched up by name, the second task called `x` in one list is
+/// paired with the second `x` in the others
+fn keyed(list: &TaskList) -> Vec<((String, usize), Fields)> {
+    let mut keyed: Vec<((String, usize), Fields)> = vec![];
+    for item in list.iter() {
+        let nth = keyed
+            .iter()
+            .filter(|((name, _), _)| name == item.name())
+            .count();
+        keyed.push(((item.name().to_string(), nth), Fields::new(&item)));
+    }
+    keyed
+}
+
+fn find<'a>(list: &'a [((String, usize), Fields)], key: &(String, usize)) -> Option<&'a Fields> {
+    list.iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, fields)| fields)
+}
+
+fn write_conflict(out: &mut String, ours: &str, theirs: &str) {
+    _ = writeln!(out, "<<<<<<< ours");
+    out.push_str(ours);
+    _ = writeln!(out, "=======");
+    out.push_str(theirs);
+    _ = writeln!(out, ">>>>>>> theirs");
+}
+
+#[derive(Debug)]
+pub struct Merge {
+    /// the merged `.tl` source, with conflict markers around every clash
+    pub text: String,
+    pub conflicts: usize,
+}
+
+impl Merge {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts == 0
+    }
+
+    pub fn list(&self) -> Result<TaskList, TaskError> {
+        if !self.is_clean() {
+            return Err(TaskError::ParseError(format!(
+                "merge has {} unresolved conflicts",
+                self.conflicts
+            )));
+        }
+        if self.text.trim().is_empty() {
+            return Ok(TaskList::new());
+        }
+        TaskList::deserialize(self.text.clone())
+    }
+}
+
+/// Three-way merge at the `TaskItem` level. Edits to different tasks, or to
+/// different fields of the same task, are combined; only a field changed in
+/// different ways on both sides (or a task edited on one side and deleted on
+/// the other) is a conflict.
+pub fn merge(base: &TaskList, ours: &TaskList, theirs: &TaskList) -> Merge {
+    let (base, ours, theirs) = (keyed(base), keyed(ours), keyed(theirs));
+    let mut merge = Merge {
+        text: String::new(),
+        conflicts: 0,
+    };
+
+    let added_by_theirs = theirs
+        .iter()
+        .filter(|(key, _)| find(&ours, key).is_none() && find(&base, key).is_none());
+    let tasks = ours.iter().chain(added_by_theirs);
+
+    for (key, _) in tasks {
+        let (b, o, t) = (find(&base, key), find(&ours, key), find(&theirs, key));
+        match (b, o, t) {
+            (_, Some(o), Some(t)) => merge_fields(&mut merge, b, o, t),
+            // deleted on one side and untouched on the other
+            (Some(b), Some(o), None) if b == o => {}
+            (Some(_), Some(o), None) => {
+                let mut ours = String::new();
+                o.write(&mut ours);
+                write_conflict(&mut merge.text, &ours, "");
+                merge.conflicts += 1;
+            }
+            (None, Some(o), None) => o.write(&mut merge.text),
+            (None, None, Some(t)) => t.write(&mut merge.text),
+            _ => {}
+        }
+    }
+
+    // tasks we deleted but they kept editing
+    for (key, t) in theirs.iter() {
+        let Some(b) = find(&base, key) else {
+            continue;
+        };
+        if find(&ours, key).is_none() && b != t {
+            let mut theirs = String::new();
+            t.write(&mut theirs);
+            write_conflict(&mut merge.text, "", &theirs);
+            merge.conflicts += 1;
+        }
+    }
+
+    merge
+}
+
+fn merge_fields(merge: &mut Merge, base: Option<&Fields>, ours: &Fields, theirs: &Fields) {
+    _ = writeln!(merge.text, "{}", ours.header);
+
+    let mut keys: Vec<&str> = ours.keys().collect();
+    keys.extend(theirs.keys().filter(|key| ours.get(key).is_none()));
+
+    for key in keys {
+        let b = base.and_then(|base| base.get(key));
+        let (o, t) = (ours.get(key), theirs.get(key));
+        let appended = match APPEND_ONLY.contains(&key) {
+            true => merge_appended(b, o, t),
+            false => None,
+        };
+        let resolved = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else if appended.is_some() {
+            appended.as_deref()
+        } else {
+            let line = |value: Option<&str>| value.map(|v| format!("{v}\n")).unwrap_or_default();
+            write_conflict(&mut merge.text, &line(o), &line(t));
+            merge.conflicts += 1;
+            continue;
+        };
+        if let Some(value) = resolved {
+            _ = writeln!(merge.text, "{value}");
+        }
+    }
+}
+
+/// keys that are logs, new entries are only ever added to the end
+const APPEND_ONLY: [&str; 2] = ["history", "session"];
+
+/// when both sides only added lines to the base version of an append only
+/// log the result keeps all of them
+fn merge_appended(base: Option<&str>, ours: Option<&str>, theirs: Option<&str>) -> Option<String> {
+    let base: Vec<&str> = base.map(|b| b.lines().collect()).unwrap_or_default();
+    let (ours, theirs): (Vec<&str>, Vec<&str>) =
+        (ours?.lines().collect(), theirs?.lines().collect());
+    if !ours.starts_with(&base) || !theirs.starts_with(&base) {
+        return None;
+    }
+
+    let mut merged = ours.clone();
+    merged.extend(
+        theirs[base.len()..]
+            .iter()
+            .filter(|line| !ours.contains(line)),
+    );
+    Some(merged.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::merge;
+    use crate::tasks::TaskList;
+
+    fn list(source: &str) -> TaskList {
+        TaskList::deserialize(source.to_string()).unwrap()
+    }
+
+    #[test]
+    pub fn test_merge_independent_edits() {
+        let base = list("[a]\nstatus = false\ndata = \"a\"\n[b]\nstatus = false\ndata = \"b\"\n");
+        let ours = list("[a]\nstatus = true\ndata = \"a\"\n[b]\nstatus = false\ndata = \"b\"\n");
+        let theirs = list(
+            "[a]\nstatus = false\ndata = \"new\"\n[c]\nstatus = false\ndata = \"c\"\n[b]\nstatus = false\ndata = \"b\"\n",
+        );
+
+        let merged = merge(&base, &ours, &theirs);
+        assert!(merged.is_clean(), "{}", merged.text);
+        let merged = merged.list().unwrap();
+        let names: Vec<_> = merged.iter().map(|t| t.name().to_string()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(merged.get(0).unwrap().status());
+        assert_eq!(merged.get(0).unwrap().data(), "new");
+    }
+
+    #[test]
+    pub fn test_merge_deletions() {
+        let base = list("[a]\nstatus = false\ndata = \"a\"\n[b]\nstatus = false\ndata = \"b\"\n");
+        let ours = list("[b]\nstatus = false\ndata = \"b\"\n");
+        let theirs = base.clone();
+        let merged = merge(&base, &ours, &theirs).list().unwrap();
+        assert_eq!(merged.len(), 1);
+
+        // they edited the task we deleted
+        let theirs = list("[a]\nstatus = true\ndata = \"a\"\n[b]\nstatus = false\ndata = \"b\"\n");
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    pub fn test_merge_field_conflict() {
+        let base = list("[a]\nstatus = false\ndata = \"a\"\n");
+        let ours = list("[a]\nstatus = true\ndata = \"ours\"\n");
+        let theirs = list("[a]\nstatus = true\ndata = \"theirs\"\n");
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "[a]\nstatus = true\n<<<<<<< ours\ndata = \"ours\"\n=======\ndata = \"theirs\"\n>>>>>>> theirs\n"
+        );
+        assert!(merged.list().is_err());
+    }
+
+    #[test]
+    pub fn test_merge_keeps_multi_line_data() {
+        let source =
+            "[a]\nstatus = false\ndata = \"a\nb\nc\nb\nstatus = maybe\nd\"\nhistory = \"1 data\"\n";
+        let base = list(source);
+        let merged = merge(&base, &base.clone(), &base.clone());
+        assert!(merged.is_clean(), "{}", merged.text);
+        assert_eq!(merged.text, source);
+        let merged = merged.list().unwrap();
+        assert_eq!(
+            merged.get(0).unwrap().data(),
+            "a\nb\nc\nb\nstatus = maybe\nd"
+        );
+        assert!(!merged.get(0).unwrap().status());
+    }
+
+    #[test]
+    pub fn test_merge_added_field_conflict() {
+        let base = list("[a]\nstatus = false\ndata = \"\"\n");
+        let ours = list("[a]\nstatus = false\ndata = \"\"\ndue = \"2024-03-01\"\n");
+        let theirs = list("[a]\nstatus = false\ndata = \"\"\ndue = \"2024-04-01\"\n");
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(merged.conflicts, 1, "{}", merged.text);
+    }
+
+    #[test]
+    pub fn test_merge_history_is_appended() {
+        let base = list("[a]\nstatus = false\ndata = \"\"\nhistory = \"1 data\"\n");
+        let ours = list(
+            "[a]\nstatus = true\ndata = \"\"\nhistory = \"1 data\"\nhistory = \"2 status true\"\n",
+        );
+        let theirs =
+            list("[a]\nstatus = false\ndata = \"x\"\nhistory = \"1 data\"\nhistory = \"3 data\"\n");
+
+        let merged = merge(&base, &ours, &theirs);
+        assert!(merged.is_clean(), "{}", merged.text);
+        let history = merged.list().unwrap().get(0).unwrap().history().to_vec();
+        let at: Vec<_> = history.iter().map(|change| change.at).collect();
+        assert_eq!(at, [1, 2, 3]);
+    }
+}
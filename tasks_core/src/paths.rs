@@ -0,0 +1,60 @@
+//! Where things live by default, following the XDG base directory spec.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// `$var` if it is set to an absolute path, otherwise `$HOME/fallback`
+fn xdg_dir(var: Option<OsString>, home: Option<OsString>, fallback: &str) -> Option<PathBuf> {
+    var.map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.map(|home| Path::new(&home).join(fallback)))
+        .map(|dir| dir.join("tasks"))
+}
+
+fn dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    xdg_dir(std::env::var_os(var), std::env::var_os("HOME"), fallback)
+}
+
+/// `$XDG_DATA_HOME/tasks`
+pub fn data_dir() -> Option<PathBuf> {
+    dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_STATE_HOME/tasks`, logs go here
+pub fn state_dir() -> Option<PathBuf> {
+    dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CONFIG_HOME/tasks`
+pub fn config_dir() -> Option<PathBuf> {
+    dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// the task file used when none is given
+pub fn default_task_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("tasks.tl"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::xdg_dir;
+
+    #[test]
+    pub fn test_xdg_dir() {
+        let home = || Some("/home/me".into());
+        assert_eq!(
+            xdg_dir(Some("/data".into()), home(), ".local/share"),
+            Some(PathBuf::from("/data/tasks"))
+        );
+        // relative paths are invalid according to the spec
+        assert_eq!(
+            xdg_dir(Some("data".into()), home(), ".local/share"),
+            Some(PathBuf::from("/home/me/.local/share/tasks"))
+        );
+        assert_eq!(xdg_dir(None, None, ".local/share"), None);
+    }
+}
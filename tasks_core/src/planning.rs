@@ -0,0 +1,179 @@
+use crate::tasks::TaskList;
+
+/// six hours of work a day unless configured otherwise
+pub const DEFAULT_CAPACITY: u64 = 6 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayPlan {
+    pub day: i64,
+    /// indices of the open tasks planned for this day
+    pub tasks: Vec<usize>,
+    /// sum of their estimates in seconds, tasks without one count as nothing
+    pub estimated: u64,
+    pub capacity: u64,
+}
+
+impl DayPlan {
+    pub fn is_overbooked(&self) -> bool {
+        self.estimated > self.capacity
+    }
+
+    pub fn free(&self) -> u64 {
+        self.capacity.saturating_sub(self.estimated)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub days: Vec<DayPlan>,
+    /// open tasks with no day, or one that has already passed
+    pub unplanned: Vec<usize>,
+}
+
+impl Plan {
+    /// plans `days` days starting at `from`, finished tasks are left out
+    pub fn new(list: &TaskList, from: i64, days: usize, capacity: u64) -> Self {
+        let mut plan = Self {
+            days: (from..from + days as i64)
+                .map(|day| DayPlan {
+                    day,
+                    tasks: vec![],
+                    estimated: 0,
+                    capacity,
+                })
+                .collect(),
+            unplanned: vec![],
+        };
+
+        for (index, item) in list.iter().enumerate() {
+            if item.status() {
+                continue;
+            }
+            match item.planned() {
+                Some(day) if day >= from => {
+                    if let Some(plan) = plan.days.iter_mut().find(|plan| plan.day == day) {
+                        plan.tasks.push(index);
+                        plan.estimated += item.estimate().unwrap_or_default();
+                    }
+                }
+                _ => plan.unplanned.push(index),
+            }
+        }
+        plan
+    }
+
+    pub fn overbooked(&self) -> impl Iterator<Item = &DayPlan> {
+        self.days.iter().filter(|day| day.is_overbooked())
+    }
+
+    /// puts every unplanned task that has an estimate on the first day with
+    /// room left for it, in list order. Returns the `(index, day)` pairs that
+    /// were assigned, tasks that fit nowhere stay unplanned.
+    pub fn schedule(&mut self, list: &TaskList) -> Vec<(usize, i64)> {
+        let mut assigned = vec![];
+        self.unplanned.retain(|index| {
+            let Some(estimate) = list.get(*index).and_then(|item| item.estimate()) else {
+                return true;
+            };
+            let Some(day) = self.days.iter_mut().find(|day| day.free() >= estimate) else {
+                return true;
+            };
+            day.tasks.push(*index);
+            day.estimated += estimate;
+            assigned.push((*index, day.day));
+            false
+        });
+        assigned
+    }
+}
+
+/// how a task's estimate held up against the time tracked on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accuracy {
+    pub index: usize,
+    pub estimate: u64,
+    pub tracked: u64,
+}
+
+impl Accuracy {
+    /// tracked time as a share of the estimate, above 1.0 means it took longer
+    pub fn ratio(&self) -> f64 {
+        if self.estimate == 0 {
+            return 0.0;
+        }
+        self.tracked as f64 / self.estimate as f64
+    }
+}
+
+/// every task with an estimate
+pub fn accuracy(list: &TaskList, now: u64) -> Vec<Accuracy> {
+    list.iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            Some(Accuracy {
+                index,
+                estimate: item.estimate()?,
+                tracked: item.tracked(now),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{accuracy, Plan};
+    use crate::tasks::{TaskItem, TaskList};
+
+    const HOUR: u64 = 60 * 60;
+
+    fn task(estimate: Option<u64>, planned: Option<i64>) -> TaskItem {
+        let mut item = TaskItem::new("task".into(), "".into(), false);
+        item.set_estimate(estimate);
+        item.set_planned(planned);
+        item
+    }
+
+    #[test]
+    pub fn test_plan() {
+        let mut list = TaskList::new();
+        list.push(task(Some(4 * HOUR), Some(10)));
+        list.push(task(Some(3 * HOUR), Some(10)));
+        list.push(task(Some(2 * HOUR), Some(8)));
+        list.push(task(Some(5 * HOUR), None));
+        list.push(task(None, None));
+        list.push(task(Some(HOUR), Some(30)));
+        let mut done = task(Some(HOUR), Some(11));
+        done.set_status(true);
+        list.push(done);
+
+        let mut plan = Plan::new(&list, 10, 3, 6 * HOUR);
+        assert_eq!(plan.days[0].tasks, [0, 1]);
+        assert_eq!(plan.days[0].estimated, 7 * HOUR);
+        assert!(plan.days[1].tasks.is_empty());
+        // planned for a day that has passed
+        assert_eq!(plan.unplanned, [2, 3, 4]);
+        assert_eq!(
+            plan.overbooked().map(|day| day.day).collect::<Vec<_>>(),
+            [10]
+        );
+
+        let assigned = plan.schedule(&list);
+        assert_eq!(assigned, [(2, 11), (3, 12)]);
+        assert_eq!(plan.unplanned, [4]);
+        assert_eq!(plan.days[1].free(), 4 * HOUR);
+    }
+
+    #[test]
+    pub fn test_accuracy() {
+        let mut list = TaskList::new();
+        let mut item = task(Some(HOUR), None);
+        item.start_timer(0);
+        item.stop_timer(HOUR + HOUR / 2);
+        list.push(item);
+        list.push(task(None, None));
+
+        let accuracy = accuracy(&list, 2 * HOUR);
+        assert_eq!(accuracy.len(), 1);
+        assert_eq!(accuracy[0].ratio(), 1.5);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fmt::Display, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Work => f.write_str("work"),
+            Phase::Break => f.write_str("break"),
+        }
+    }
+}
+
+/// A focus session countdown, it starts with a work phase and alternates
+/// between work and breaks for as long as it is ticked
+#[derive(Debug, Clone)]
+pub struct Pomodoro {
+    work: Duration,
+    rest: Duration,
+    phase: Phase,
+    remaining: Duration,
+    paused: bool,
+}
+
+impl Pomodoro {
+    pub const WORK: Duration = Duration::from_secs(25 * 60);
+    pub const BREAK: Duration = Duration::from_secs(5 * 60);
+
+    pub fn new(work: Duration, rest: Duration) -> Self {
+        Self {
+            work,
+            rest,
+            phase: Phase::Work,
+            remaining: work,
+            paused: false,
+        }
+    }
+
+    /// advances the countdown, returns the phase that just ran out
+    pub fn tick(&mut self, dt: Duration) -> Option<Phase> {
+        if self.paused {
+            return None;
+        }
+        self.remaining = self.remaining.saturating_sub(dt);
+        if !self.remaining.is_zero() {
+            return None;
+        }
+
+        let finished = self.phase;
+        (self.phase, self.remaining) = match finished {
+            Phase::Work => (Phase::Break, self.rest),
+            Phase::Break => (Phase::Work, self.work),
+        };
+        Some(finished)
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused
+    }
+}
+
+impl Default for Pomodoro {
+    fn default() -> Self {
+        Pomodoro::new(Pomodoro::WORK, Pomodoro::BREAK)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Phase, Pomodoro};
+
+    #[test]
+    pub fn test_phases() {
+        let secs = Duration::from_secs;
+        let mut pomodoro = Pomodoro::new(secs(10), secs(3));
+
+        assert_eq!(pomodoro.tick(secs(9)), None);
+        pomodoro.toggle_pause();
+        assert_eq!(pomodoro.tick(secs(60)), None);
+        pomodoro.toggle_pause();
+
+        assert_eq!(pomodoro.tick(secs(1)), Some(Phase::Work));
+        assert_eq!(pomodoro.phase(), Phase::Break);
+        assert_eq!(pomodoro.remaining(), secs(3));
+        assert_eq!(pomodoro.tick(secs(5)), Some(Phase::Break));
+        assert_eq!(pomodoro.phase(), Phase::Work);
+    }
+}
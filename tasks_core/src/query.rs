@@ -0,0 +1,103 @@
+use crate::{
+    clock,
+    tasks::{Priority, TaskError, TaskItem, TaskList},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// matches the name or data, ignoring case
+    Text(String),
+    Tag(String),
+    Status(bool),
+    Priority(Priority),
+    /// due on or before the day
+    Due(i64),
+    Blocked(bool),
+}
+
+/// A space separated list of terms that all have to match. `tag:x`,
+/// `status:done|open`, `priority:high`, `due:YYYY-MM-DD` (due by that day)
+/// and `blocked:true|false` look at fields, anything else is searched for in
+/// the name and data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Self, TaskError> {
+        let err = |term: &str, expected: &str| {
+            TaskError::ParseError(format!("{term}: expected {expected}"))
+        };
+        let mut terms = vec![];
+        for word in source.split_whitespace() {
+            let term = match word.split_once(':') {
+                Some(("tag", tag)) => Term::Tag(tag.to_string()),
+                Some(("status", "done")) => Term::Status(true),
+                Some(("status", "open")) => Term::Status(false),
+                Some(("status", _)) => return Err(err(word, "done or open")),
+                Some(("priority", priority)) => Term::Priority(Priority::parse(priority)?),
+                Some(("due", due)) => {
+                    Term::Due(clock::parse_date(due).ok_or_else(|| err(word, "a YYYY-MM-DD date"))?)
+                }
+                Some(("blocked", blocked)) => {
+                    Term::Blocked(blocked.parse().map_err(|_| err(word, "true or false"))?)
+                }
+                _ => Term::Text(word.to_lowercase()),
+            };
+            terms.push(term);
+        }
+        Ok(Self { terms })
+    }
+
+    pub fn matches(&self, item: &TaskItem) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => {
+                item.name().to_lowercase().contains(text)
+                    || item.data().to_lowercase().contains(text)
+            }
+            Term::Tag(tag) => item.tags().iter().any(|t| t == tag),
+            Term::Status(status) => item.status() == *status,
+            Term::Priority(priority) => item.priority() == Some(*priority),
+            Term::Due(day) => item.due().is_some_and(|due| due <= *day),
+            Term::Blocked(blocked) => item.blocked() == *blocked,
+        })
+    }
+
+    /// indices of every matching task in `list`
+    pub fn filter(&self, list: &TaskList) -> Vec<usize> {
+        list.iter()
+            .enumerate()
+            .filter(|(_, item)| self.matches(item))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Query;
+    use crate::tasks::TaskList;
+
+    #[test]
+    pub fn test_query() {
+        let list = TaskList::deserialize(
+            "[Write report]\nstatus = false\ndata = \"quarterly numbers\"\ntags = \"work\"\ndue = \"2024-03-01\"\n\
+            [Groceries]\nstatus = true\ndata = \"\"\npriority = \"high\"\n\
+            [report bug]\nstatus = false\ndata = \"\"\ntags = \"work,oss\"\n"
+                .to_string(),
+        )
+        .unwrap();
+
+        let filter = |source: &str| Query::parse(source).unwrap().filter(&list);
+        assert_eq!(filter("report"), [0, 2]);
+        assert_eq!(filter("NUMBERS"), [0]);
+        assert_eq!(filter("tag:work status:open"), [0, 2]);
+        assert_eq!(filter("tag:oss"), [2]);
+        assert_eq!(filter("status:done priority:high"), [1]);
+        assert_eq!(filter("due:2024-03-01"), [0]);
+        assert_eq!(filter("due:2024-02-01"), [] as [usize; 0]);
+        assert_eq!(filter(""), [0, 1, 2]);
+        assert!(Query::parse("status:maybe").is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use rusqlite::{params, types::Type, Connection, Row};
+
+use crate::{
+    store::{acquire_lock, watch_file, FileStore, StoreEvent, TaskStore},
+    tasks::{TaskError, TaskItem, TaskList},
+};
+
+/// Each entry moves the schema up by one version, the current version is kept
+/// in `PRAGMA user_version`. Never edit an entry once it has shipped, append a
+/// new one instead.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE tasks (
+        id INTEGER PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        status INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX tasks_status ON tasks(status);
+    CREATE INDEX tasks_name ON tasks(name);",
+    // 2: timestamps get their own columns so they can be queried, everything
+    // else a task carries is kept as its `.tl` field lines
+    "ALTER TABLE tasks ADD COLUMN created_at INTEGER;
+    ALTER TABLE tasks ADD COLUMN completed_at INTEGER;
+    ALTER TABLE tasks ADD COLUMN fields TEXT NOT NULL DEFAULT '';
+    CREATE INDEX tasks_completed_at ON tasks(completed_at);",
+];
+
+/// whether two lists would be stored the same
+fn same(a: &TaskList, b: &TaskList) -> Result<bool, TaskError> {
+    let (mut left, mut right) = (vec![], vec![]);
+    a.serialize(&mut left)?;
+    b.serialize(&mut right)?;
+    Ok(left == right)
+}
+
+impl From<rusqlite::Error> for TaskError {
+    fn from(err: rusqlite::Error) -> Self {
+        TaskError::Database(err.to_string())
+    }
+}
+
+/// A store backed by an embedded SQLite database
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Connection,
+    path: Option<PathBuf>,
+    lock: Option<File>,
+    base: TaskList,
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TaskError> {
+        let conn = Connection::open(path.as_ref())?;
+        SqliteStore::with_connection(conn, Some(path.as_ref().to_path_buf()))
+    }
+
+    pub fn open_in_memory() -> Result<Self, TaskError> {
+        SqliteStore::with_connection(Connection::open_in_memory()?, None)
+    }
+
+    fn with_connection(mut conn: Connection, path: Option<PathBuf>) -> Result<Self, TaskError> {
+        SqliteStore::migrate(&mut conn)?;
+        Ok(Self {
+            conn,
+            path,
+            lock: None,
+            base: TaskList::new(),
+        })
+    }
+
+    fn migrate(conn: &mut Connection) -> Result<(), TaskError> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(TaskError::Database(format!(
+                "schema version {version} is newer than this build supports ({})",
+                MIGRATIONS.len()
+            )));
+        }
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tracing::info!("running sqlite migration {}", i + 1);
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    pub fn schema_version(&self) -> Result<usize, TaskError> {
+        Ok(self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    /// appends every task from a `.tl` file to the database
+    pub fn import_tl<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, TaskError> {
+        let imported = FileStore::new(path).load()?;
+        let mut list = self.load()?;
+        let count = imported.len();
+        imported.iter().for_each(|item| list.push(item));
+        self.save(&list)?;
+        Ok(count)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Result<TaskList, TaskError> {
+        self.query(
+            "SELECT name, status, data, fields FROM tasks WHERE name = ?1 ORDER BY position",
+            params![name],
+        )
+    }
+
+    pub fn find_by_status(&self, status: bool) -> Result<TaskList, TaskError> {
+        self.query(
+            "SELECT name, status, data, fields FROM tasks WHERE status = ?1 ORDER BY position",
+            params![status],
+        )
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<TaskList, TaskError> {
+        SqliteStore::query_with(&self.conn, sql, params)
+    }
+
+    fn query_with(
+        conn: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<TaskList, TaskError> {
+        let mut stmt = conn.prepare(sql)?;
+        let mut list = TaskList::new();
+        for item in stmt.query_map(params, SqliteStore::item_from_row)? {
+            list.push(item?);
+        }
+        Ok(list)
+    }
+
+    pub fn completed_between(&self, from: u64, to: u64) -> Result<TaskList, TaskError> {
+        self.query(
+            "SELECT name, status, data, fields FROM tasks
+            WHERE completed_at >= ?1 AND completed_at < ?2 ORDER BY position",
+            params![from, to],
+        )
+    }
+
+    fn all(conn: &Connection) -> Result<TaskList, TaskError> {
+        SqliteStore::query_with(
+            conn,
+            "SELECT name, status, data, fields FROM tasks ORDER BY position",
+            [],
+        )
+    }
+
+    /// replaces every row with `list`, when `check` is set this fails with
+    /// `TaskError::Conflict` if the rows are no longer the ones we last
+    /// loaded or saved. Both happen in one transaction so no other writer
+    /// can slip in between.
+    fn write(&mut self, list: &TaskList, check: bool) -> Result<(), TaskError> {
+        let tx = self.conn.transaction()?;
+        if check && !same(&SqliteStore::all(&tx)?, &self.base)? {
+            let name = match &self.path {
+                Some(path) => path.display().to_string(),
+                None => ":memory:".to_string(),
+            };
+            return Err(TaskError::Conflict(name));
+        }
+        tx.execute("DELETE FROM tasks", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO tasks (position, name, status, data, created_at, completed_at, fields)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (position, item) in list.iter().enumerate() {
+                let mut fields = vec![];
+                item.serialize_fields(&mut fields)?;
+                stmt.execute(params![
+                    position,
+                    item.name(),
+                    item.status(),
+                    item.data(),
+                    item.created_at(),
+                    item.completed_at(),
+                    String::from_utf8_lossy(&fields),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        self.base = list.clone();
+        Ok(())
+    }
+
+    fn item_from_row(row: &Row) -> rusqlite::Result<TaskItem> {
+        let fields: String = row.get(3)?;
+        TaskItem::from_parts(row.get(0)?, row.get(1)?, row.get(2)?, &fields).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(3, Type::Text, err.to_string().into())
+        })
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&mut self) -> Result<TaskList, TaskError> {
+        let list = self.read()?;
+        self.base = list.clone();
+        Ok(list)
+    }
+
+    fn read(&self) -> Result<TaskList, TaskError> {
+        SqliteStore::all(&self.conn)
+    }
+
+    fn base(&self) -> TaskList {
+        self.base.clone()
+    }
+
+    fn save(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        self.write(list, true)
+    }
+
+    fn overwrite(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        self.write(list, false)
+    }
+
+    fn watch(&mut self) -> Result<Receiver<StoreEvent>, TaskError> {
+        match &self.path {
+            Some(path) => watch_file(path.clone()),
+            // nobody else can reach an in-memory database
+            None => Ok(channel().1),
+        }
+    }
+
+    fn lock(&mut self) -> Result<(), TaskError> {
+        if let (None, Some(path)) = (&self.lock, &self.path) {
+            self.lock = Some(acquire_lock(path)?);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), TaskError> {
+        self.lock = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SqliteStore, MIGRATIONS};
+    use crate::{
+        store::TaskStore,
+        tasks::{TaskError, TaskItem, TaskList},
+    };
+
+    #[test]
+    pub fn test_sqlite_round_trip() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+
+        let mut list = TaskList::new();
+        list.push(TaskItem::new("task1".into(), "data".into(), false));
+        list.push(TaskItem::new("task2".into(), "".into(), true));
+        list.push(TaskItem::new("task1".into(), "again".into(), true));
+        store.save(&list).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get(2).unwrap().data(), "again");
+
+        assert_eq!(store.find_by_name("task1").unwrap().len(), 2);
+        assert_eq!(store.find_by_status(true).unwrap().len(), 2);
+
+        let done = store.completed_between(0, i64::MAX as u64).unwrap();
+        assert_eq!(done.len(), 2);
+        assert_eq!(
+            done.get(0).unwrap().created_at(),
+            list.get(1).unwrap().created_at()
+        );
+    }
+
+    #[test]
+    pub fn test_sqlite_import() {
+        let dir = std::env::temp_dir().join(format!("tasks_sqlite_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tl = dir.join("tasks.tl");
+        std::fs::write(
+            &tl,
+            "[a]\nstatus = false\ndata = \"x\"\n[b]\nstatus = true\ndata = \"y\"\n",
+        )
+        .unwrap();
+
+        let mut store = SqliteStore::open(dir.join("tasks.db")).unwrap();
+        assert_eq!(store.import_tl(&tl).unwrap(), 2);
+        drop(store);
+
+        // reopening must not rerun migrations or lose rows
+        let mut store = SqliteStore::open(dir.join("tasks.db")).unwrap();
+        assert_eq!(store.load().unwrap().len(), 2);
+        drop(store);
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_sqlite_conflicts() {
+        let dir =
+            std::env::temp_dir().join(format!("tasks_sqlite_conflict_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.db");
+
+        let mut ours = SqliteStore::open(&path).unwrap();
+        let mut theirs = SqliteStore::open(&path).unwrap();
+        let mut list = ours.load().unwrap();
+        let mut other = theirs.load().unwrap();
+
+        other.push(TaskItem::new("theirs".into(), "".into(), false));
+        theirs.save(&other).unwrap();
+
+        list.push(TaskItem::new("ours".into(), "".into(), false));
+        assert_eq!(
+            ours.save(&list),
+            Err(TaskError::Conflict(path.display().to_string()))
+        );
+        // their row is still there
+        assert_eq!(ours.read().unwrap().get(0).unwrap().name(), "theirs");
+
+        let merged = ours.merge(&list).unwrap();
+        ours.overwrite(&merged).unwrap();
+        assert_eq!(ours.load().unwrap().len(), 2);
+        // and now the other store is the one that is behind
+        assert!(theirs.save(&other).is_err());
+
+        drop((ours, theirs));
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
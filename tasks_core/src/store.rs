@@ -0,0 +1,599 @@
+use std::{
+    fs::{File, OpenOptions},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::{
+    merge,
+    tasks::{TaskError, TaskList},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StoreEvent {
+    Changed,
+}
+
+/// Where a `TaskList` lives between runs. Frontends only talk to a store,
+/// they never read or write task files themselves.
+pub trait TaskStore {
+    fn load(&mut self) -> Result<TaskList, TaskError>;
+
+    /// reads what is stored right now without counting it as loaded, so a
+    /// later `save` still notices the external change
+    fn read(&self) -> Result<TaskList, TaskError>;
+
+    /// the list as it was last loaded or saved
+    fn base(&self) -> TaskList;
+
+    /// fails with `TaskError::Conflict` when the storage was changed by
+    /// someone else since the last `load` or `save`
+    fn save(&mut self, list: &TaskList) -> Result<(), TaskError>;
+
+    /// saves without checking for external changes
+    fn overwrite(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        self.save(list)
+    }
+
+    /// three-way merges `list` and the external version, using `base` as
+    /// their common ancestor. The result has to be written with `overwrite`
+    /// by the caller, when the two clash nothing is merged.
+    fn merge(&mut self, list: &TaskList) -> Result<TaskList, TaskError> {
+        let theirs = self.read()?;
+        merge::merge(&self.base(), list, &theirs).list()
+    }
+
+    /// returns a receiver that gets a `StoreEvent::Changed` every time the
+    /// underlying storage is modified, our own saves show up as well
+    fn watch(&mut self) -> Result<Receiver<StoreEvent>, TaskError>;
+
+    fn lock(&mut self) -> Result<(), TaskError>;
+
+    fn unlock(&mut self) -> Result<(), TaskError>;
+}
+
+fn deserialize(source: String) -> Result<TaskList, TaskError> {
+    if source.trim().is_empty() {
+        return Ok(TaskList::new());
+    }
+    TaskList::deserialize(source)
+}
+
+/// watches the directory holding `path` with inotify (or the platform
+/// equivalent) and reports changes to that one file. The directory is watched
+/// instead of the file because editors and our own saves replace the file
+/// with a rename, which would silently end a watch on the old inode.
+pub(crate) fn watch_file(path: PathBuf) -> Result<Receiver<StoreEvent>, TaskError> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = path.file_name().map(|name| name.to_os_string());
+
+    let (events_tx, events) = channel();
+    let mut watcher =
+        notify::recommended_watcher(events_tx).map_err(|e| TaskError::Io(e.to_string()))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| TaskError::Io(e.to_string()))?;
+
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        // keep the watcher alive for as long as the thread runs
+        let _watcher = watcher;
+        for event in events {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !(event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove()) {
+                continue;
+            }
+            if !event
+                .paths
+                .iter()
+                .any(|changed| changed.file_name() == name.as_deref())
+            {
+                continue;
+            }
+            if tx.send(StoreEvent::Changed).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(suffix);
+    path.into()
+}
+
+/// takes an advisory lock on `<path>.lock`, the lock is held for as long as
+/// the returned file stays open and is released by the OS if we crash
+pub(crate) fn acquire_lock(path: &Path) -> Result<File, TaskError> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(with_suffix(path, ".lock"))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+        // SAFETY: the descriptor is owned by `file` and valid for the call
+        let res = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+        if res != 0 {
+            return Err(TaskError::Locked(path.display().to_string()));
+        }
+    }
+    Ok(file)
+}
+
+/// what the file looked like the last time we read or wrote it. Only the
+/// contents count, timestamps are too coarse on some file systems to notice
+/// two writes in quick succession.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fingerprint {
+    hash: u64,
+}
+
+impl Fingerprint {
+    fn of(source: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+        }
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let source = std::fs::read_to_string(path).ok()?;
+        Some(Fingerprint::of(&source))
+    }
+}
+
+/// A store backed by a `.tl` file.
+///
+/// Saves go to a temporary file that is synced and then renamed over the
+/// task file, so a crash can never leave a half written list behind. The
+/// previous versions are kept as `tasks.tl.1` (newest) up to `tasks.tl.N`.
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    lock: Option<File>,
+    backups: usize,
+    seen: Option<Fingerprint>,
+    base: TaskList,
+}
+
+impl FileStore {
+    pub const DEFAULT_BACKUPS: usize = 3;
+
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: None,
+            backups: FileStore::DEFAULT_BACKUPS,
+            seen: None,
+            base: TaskList::new(),
+        }
+    }
+
+    /// creates an empty task file, and the directories leading up to it, if
+    /// there is none yet
+    pub fn create_if_missing(&self) -> Result<(), TaskError> {
+        if self.path.exists() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        Ok(())
+    }
+
+    /// sets how many rotating backups are kept, 0 disables them
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn backup_path(&self, n: usize) -> PathBuf {
+        with_suffix(&self.path, &format!(".{n}"))
+    }
+
+    /// the backups that currently exist on disk, newest first
+    pub fn backups(&self) -> Vec<PathBuf> {
+        (1..=self.backups)
+            .map(|n| self.backup_path(n))
+            .take_while(|path| path.exists())
+            .collect()
+    }
+
+    /// replaces the task file with backup `n` and returns its contents,
+    /// the file being replaced becomes the newest backup
+    pub fn restore(&mut self, n: usize) -> Result<TaskList, TaskError> {
+        let path = self.backup_path(n);
+        let source = std::fs::read_to_string(&path)
+            .map_err(|_| TaskError::NoFile(path.display().to_string()))?;
+        let list = deserialize(source)?;
+        self.overwrite(&list)?;
+        Ok(list)
+    }
+
+    /// true when the file differs from what we last loaded or saved, a
+    /// touched file with the same contents is not considered changed
+    pub fn changed_on_disk(&self) -> bool {
+        let current = Fingerprint::read(&self.path);
+        match (self.seen, current) {
+            (None, None) => false,
+            (Some(seen), Some(current)) => seen != current,
+            _ => true,
+        }
+    }
+
+    fn rotate_backups(&self) -> Result<(), TaskError> {
+        if self.backups == 0 || !self.path.exists() {
+            return Ok(());
+        }
+        for n in (1..self.backups).rev() {
+            let from = self.backup_path(n);
+            if from.exists() {
+                std::fs::rename(from, self.backup_path(n + 1))?;
+            }
+        }
+        std::fs::copy(&self.path, self.backup_path(1))?;
+        Ok(())
+    }
+
+    fn sync_parent(&self) -> Result<(), TaskError> {
+        #[cfg(unix)]
+        if let Some(parent) = self.path.parent() {
+            let parent = match parent.as_os_str().is_empty() {
+                true => Path::new("."),
+                false => parent,
+            };
+            std::fs::File::open(parent)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskStore for FileStore {
+    fn load(&mut self) -> Result<TaskList, TaskError> {
+        let source = std::fs::read_to_string(&self.path)
+            .map_err(|_| TaskError::NoFile(self.path.display().to_string()))?;
+        let fingerprint = Fingerprint::of(&source);
+        let list = deserialize(source)?;
+        self.seen = Some(fingerprint);
+        self.base = list.clone();
+        Ok(list)
+    }
+
+    fn read(&self) -> Result<TaskList, TaskError> {
+        let source = std::fs::read_to_string(&self.path)
+            .map_err(|_| TaskError::NoFile(self.path.display().to_string()))?;
+        deserialize(source)
+    }
+
+    fn base(&self) -> TaskList {
+        self.base.clone()
+    }
+
+    fn save(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        if self.changed_on_disk() {
+            return Err(TaskError::Conflict(self.path.display().to_string()));
+        }
+        self.overwrite(list)
+    }
+
+    fn overwrite(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        let mut source = vec![];
+        list.serialize(&mut source)?;
+
+        let tmp = with_suffix(&self.path, ".tmp");
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)?;
+        file.write_all(&source)?;
+        file.sync_all()?;
+        drop(file);
+
+        self.rotate_backups()?;
+        std::fs::rename(&tmp, &self.path)?;
+        self.sync_parent()?;
+
+        let source = String::from_utf8_lossy(&source);
+        self.seen = Some(Fingerprint::of(&source));
+        self.base = list.clone();
+        Ok(())
+    }
+
+    fn watch(&mut self) -> Result<Receiver<StoreEvent>, TaskError> {
+        watch_file(self.path.clone())
+    }
+
+    fn lock(&mut self) -> Result<(), TaskError> {
+        if self.lock.is_none() {
+            self.lock = Some(acquire_lock(&self.path)?);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), TaskError> {
+        self.lock = None;
+        Ok(())
+    }
+}
+
+/// A store that keeps the serialized list in memory, mostly useful for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    source: String,
+    locked: bool,
+    changed: bool,
+    base: TaskList,
+    watchers: Vec<Sender<StoreEvent>>,
+}
+
+impl MemoryStore {
+    pub fn new(source: String) -> Self {
+        Self {
+            source,
+            ..Default::default()
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// replaces the contents as if another process had written them
+    pub fn set_source(&mut self, source: String) {
+        self.source = source;
+        self.changed = true;
+        self.watchers
+            .retain(|watcher| watcher.send(StoreEvent::Changed).is_ok());
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn load(&mut self) -> Result<TaskList, TaskError> {
+        let list = self.read()?;
+        self.changed = false;
+        self.base = list.clone();
+        Ok(list)
+    }
+
+    fn read(&self) -> Result<TaskList, TaskError> {
+        deserialize(self.source.clone())
+    }
+
+    fn base(&self) -> TaskList {
+        self.base.clone()
+    }
+
+    fn save(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        if self.changed {
+            return Err(TaskError::Conflict("memory".to_string()));
+        }
+        self.overwrite(list)
+    }
+
+    fn overwrite(&mut self, list: &TaskList) -> Result<(), TaskError> {
+        let mut buffer = vec![];
+        list.serialize(&mut buffer)?;
+        self.source = String::from_utf8(buffer).map_err(|e| TaskError::Io(e.to_string()))?;
+        self.changed = false;
+        self.base = list.clone();
+        Ok(())
+    }
+
+    fn watch(&mut self) -> Result<Receiver<StoreEvent>, TaskError> {
+        let (tx, rx) = channel();
+        self.watchers.push(tx);
+        Ok(rx)
+    }
+
+    fn lock(&mut self) -> Result<(), TaskError> {
+        if self.locked {
+            return Err(TaskError::Locked("memory".to_string()));
+        }
+        self.locked = true;
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), TaskError> {
+        self.locked = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FileStore, MemoryStore, StoreEvent, TaskStore};
+    use crate::tasks::{TaskError, TaskItem, TaskList};
+
+    #[test]
+    pub fn test_memory_round_trip() {
+        let mut store = MemoryStore::default();
+        let mut list = TaskList::new();
+        list.push(TaskItem::new("task1".into(), "some data".into(), false));
+        list.push(TaskItem::new("task2".into(), "".into(), true));
+        store.save(&list).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get(0).unwrap().name(), "task1");
+        assert_eq!(loaded.get(0).unwrap().data(), "some data");
+        assert!(loaded.get(1).unwrap().status());
+    }
+
+    #[test]
+    pub fn test_memory_watch_and_lock() {
+        let mut store = MemoryStore::default();
+        assert!(store.load().unwrap().is_empty());
+
+        let rx = store.watch().unwrap();
+        store.set_source("[task]\nstatus = true\ndata = \"\"\n".into());
+        assert_eq!(rx.try_recv(), Ok(StoreEvent::Changed));
+
+        store.lock().unwrap();
+        assert!(store.lock().is_err());
+        store.unlock().unwrap();
+        assert!(store.lock().is_ok());
+    }
+
+    #[test]
+    pub fn test_create_if_missing() {
+        let dir = std::env::temp_dir().join(format!("tasks_create_{}", std::process::id()));
+        let mut store = FileStore::new(dir.join("nested").join("tasks.tl"));
+        store.create_if_missing().unwrap();
+        assert!(store.load().unwrap().is_empty());
+
+        let mut list = TaskList::new();
+        list.push(TaskItem::new("kept".into(), "".into(), false));
+        store.save(&list).unwrap();
+        store.create_if_missing().unwrap();
+        assert_eq!(store.load().unwrap().len(), 1);
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_file_backups_and_restore() {
+        let dir = std::env::temp_dir().join(format!("tasks_backups_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = FileStore::new(dir.join("tasks.tl")).with_backups(2);
+
+        let mut list = TaskList::new();
+        for name in ["first", "second", "third", "fourth"] {
+            list.push(TaskItem::new(name.into(), "".into(), false));
+            store.save(&list).unwrap();
+        }
+
+        // four saves with two backups keeps the two previous versions
+        assert_eq!(store.backups().len(), 2);
+        assert!(!dir.join("tasks.tl.tmp").exists());
+        assert_eq!(store.load().unwrap().len(), 4);
+
+        let restored = store.restore(2).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(store.load().unwrap().len(), 2);
+        // the version we restored over is now the newest backup
+        let newest = std::fs::read_to_string(store.backup_path(1)).unwrap();
+        assert!(newest.contains("[fourth]"));
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_conflicts() {
+        let mut store = MemoryStore::new(
+            "[a]\nstatus = false\ndata = \"\"\n[b]\nstatus = false\ndata = \"\"\n".into(),
+        );
+        let mut list = store.load().unwrap();
+        list.remove(1);
+        list.push(TaskItem::new("c".into(), "".into(), false));
+
+        store.set_source(
+            "[a]\nstatus = true\ndata = \"\"\n[b]\nstatus = false\ndata = \"\"\n[d]\nstatus = false\ndata = \"\"\n"
+                .into(),
+        );
+        assert_eq!(
+            store.save(&list),
+            Err(TaskError::Conflict("memory".to_string()))
+        );
+
+        // their edit to `a` is kept and the task we removed stays removed
+        let merged = store.merge(&list).unwrap();
+        let names: Vec<_> = merged.iter().map(|t| t.name().to_string()).collect();
+        assert_eq!(names, ["a", "c", "d"]);
+        assert!(merged.get(0).unwrap().status());
+        store.overwrite(&merged).unwrap();
+
+        // a clash merges nothing and saving still refuses
+        let mut list = store.load().unwrap();
+        list.replace(1, TaskItem::new("c".into(), "ours".into(), false));
+        store.set_source(
+            "[a]\nstatus = true\ndata = \"\"\n[c]\nstatus = false\ndata = \"theirs\"\n".into(),
+        );
+        assert!(store.merge(&list).is_err());
+        assert!(store.save(&list).is_err());
+    }
+
+    #[test]
+    pub fn test_same_mtime_is_still_a_change() {
+        let dir = std::env::temp_dir().join(format!("tasks_mtime_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.tl");
+        std::fs::write(&path, "[a]\nstatus = false\ndata = \"\"\n").unwrap();
+
+        let mut store = FileStore::new(&path);
+        store.load().unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "[a]\nstatus = true\ndata = \"\"\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert!(store.changed_on_disk());
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_file_lock_is_exclusive() {
+        let dir = std::env::temp_dir().join(format!("tasks_lock_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut first = FileStore::new(dir.join("tasks.tl"));
+        let mut second = FileStore::new(dir.join("tasks.tl"));
+
+        first.lock().unwrap();
+        assert!(matches!(second.lock(), Err(TaskError::Locked(_))));
+        first.unlock().unwrap();
+        second.lock().unwrap();
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_file_watch() {
+        let dir = std::env::temp_dir().join(format!("tasks_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tasks.tl");
+        std::fs::write(&path, "").unwrap();
+
+        let mut store = FileStore::new(&path);
+        let rx = store.watch().unwrap();
+
+        // unrelated files in the same directory are ignored
+        std::fs::write(dir.join("other.tl"), "").unwrap();
+        std::fs::write(&path, "[a]\nstatus = true\ndata = \"\"\n").unwrap();
+
+        let timeout = std::time::Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(timeout), Ok(StoreEvent::Changed));
+        assert!(store.changed_on_disk());
+        assert_eq!(store.load().unwrap().len(), 1);
+        assert!(!store.changed_on_disk());
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,11 +1,20 @@
-use crate::iterator::*;
-use std::{fmt::Display, io::Write};
+use crate::{clock, iterator::*};
+use std::{
+    fmt::Display,
+    io::Write,
+    ops::Index,
+    sync::mpsc::{channel, Receiver, Sender},
+};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TaskError {
     NoFile(String),
     NoData,
     ParseError(String),
+    Io(String),
+    Locked(String),
+    Conflict(String),
+    Database(String),
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -29,10 +38,22 @@ impl Display for TaskError {
             }
             TaskError::NoFile(file) => write!(f, "ENOFILE: {file}"),
             TaskError::NoData => write!(f, "No data to parse"),
+            TaskError::Io(err) => write!(f, "IO error: {err}"),
+            TaskError::Locked(file) => write!(f, "{file} is locked by another store"),
+            TaskError::Conflict(file) => {
+                write!(f, "{file} was changed by someone else since it was loaded")
+            }
+            TaskError::Database(err) => write!(f, "Database error: {err}"),
         }
     }
 }
 
+impl From<std::io::Error> for TaskError {
+    fn from(err: std::io::Error) -> Self {
+        TaskError::Io(err.to_string())
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 struct TaskName {
     name: String,
@@ -77,11 +98,230 @@ impl TaskStatus {
     }
 }
 
+/// One entry in a task's activity history. `value` holds the new value for
+/// short fields like `status` and `name`, and is empty for `data` edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskChange {
+    pub at: u64,
+    pub field: String,
+    pub value: String,
+}
+
+impl Display for TaskChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value.is_empty() {
+            true => write!(f, "{} {}", self.at, self.field),
+            false => write!(f, "{} {} {}", self.at, self.field, self.value),
+        }
+    }
+}
+
+impl TaskChange {
+    fn parse(source: &str) -> Result<Self, TaskError> {
+        let mut parts = source.splitn(3, ' ');
+        let at = parts
+            .next()
+            .and_then(|at| at.parse().ok())
+            .ok_or_else(|| TaskError::ParseError(format!("bad history entry: {source}")))?;
+        let field = parts.next().unwrap_or_default().to_string();
+        let value = parts.next().unwrap_or_default().to_string();
+        Ok(Self { at, field, value })
+    }
+}
+
+/// how often a task comes back after it was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    Daily,
+    Weekly,
+}
+
+impl Display for Repeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repeat::Daily => f.write_str("daily"),
+            Repeat::Weekly => f.write_str("weekly"),
+        }
+    }
+}
+
+impl Repeat {
+    pub fn parse(source: &str) -> Result<Self, TaskError> {
+        match source {
+            "daily" => Ok(Repeat::Daily),
+            "weekly" => Ok(Repeat::Weekly),
+            _ => Err(TaskError::ParseError(format!(
+                "repeat must be daily or weekly, found {source}"
+            ))),
+        }
+    }
+
+    /// the day, or week, that `day` belongs to
+    pub fn period(&self, day: i64) -> i64 {
+        match self {
+            Repeat::Daily => day,
+            Repeat::Weekly => clock::week(day),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Priority::Low => f.write_str("low"),
+            Priority::Medium => f.write_str("medium"),
+            Priority::High => f.write_str("high"),
+        }
+    }
+}
+
+impl Priority {
+    pub fn parse(source: &str) -> Result<Self, TaskError> {
+        match source {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err(TaskError::ParseError(format!(
+                "priority must be low, medium or high, found {source}"
+            ))),
+        }
+    }
+}
+
+/// A stretch of time spent on a task, `end` is `None` while the timer runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{} {end}", self.start),
+            None => write!(f, "{}", self.start),
+        }
+    }
+}
+
+impl Session {
+    fn parse(source: &str) -> Result<Self, TaskError> {
+        let err = || TaskError::ParseError(format!("bad session: {source}"));
+        let mut parts = source.split_whitespace();
+        let start = parts.next().and_then(|s| s.parse().ok()).ok_or_else(err)?;
+        let end = match parts.next() {
+            Some(end) => Some(end.parse().map_err(|_| err())?),
+            None => None,
+        };
+        Ok(Self { start, end })
+    }
+
+    /// seconds spent in this session, a running session counts up to `now`
+    pub fn duration(&self, now: u64) -> u64 {
+        self.end.unwrap_or(now).saturating_sub(self.start)
+    }
+
+    /// seconds of this session that fall on `day`
+    pub fn duration_on(&self, day: i64, now: u64) -> u64 {
+        let day_start = day.max(0) as u64 * clock::DAY;
+        let day_end = day_start + clock::DAY;
+        let start = self.start.max(day_start);
+        let end = self.end.unwrap_or(now).min(day_end);
+        end.saturating_sub(start)
+    }
+}
+
+/// characters a name or data can't contain unescaped
+const NAME_SPECIAL: [char; 2] = ['[', ']'];
+const DATA_SPECIAL: [char; 1] = ['"'];
+
+/// backslash escapes every `special` character, and each backslash that
+/// would otherwise read as an escape. Any other backslash, like the one in
+/// `\n`, is written as is so existing files keep their text.
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let needs_escape = match ch {
+            '\\' => chars
+                .peek()
+                .is_none_or(|next| *next == '\\' || special.contains(next)),
+            _ => special.contains(&ch),
+        };
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// reverses `escape`
+fn unescape(value: &str, special: &[char]) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            if let Some(next) = chars.next_if(|next| *next == '\\' || special.contains(next)) {
+                unescaped.push(next);
+                continue;
+            }
+        }
+        unescaped.push(ch);
+    }
+    unescaped
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_date(key: &str, value: &str) -> Result<i64, TaskError> {
+    clock::parse_date(unquote(value)).ok_or_else(|| {
+        TaskError::ParseError(format!("{key} must be a YYYY-MM-DD date, found {value}"))
+    })
+}
+
+fn parse_timestamp(key: &str, value: &str) -> Result<u64, TaskError> {
+    value
+        .parse()
+        .map_err(|_| TaskError::ParseError(format!("{key} must be a unix timestamp")))
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct TaskItem {
     name: String,
     status: bool,
     data: String,
+    created_at: Option<u64>,
+    completed_at: Option<u64>,
+    history: Vec<TaskChange>,
+    repeat: Option<Repeat>,
+    sessions: Vec<Session>,
+    pomodoros: usize,
+    /// expected effort in seconds
+    estimate: Option<u64>,
+    /// the day the task is planned for
+    planned: Option<i64>,
+    due: Option<i64>,
+    priority: Option<Priority>,
+    tags: Vec<String>,
+    /// waiting on something outside of this task
+    blocked: bool,
+    /// hidden from the default list until this day
+    wait: Option<i64>,
+    /// fields this version doesn't know about, written back untouched
+    extra: Vec<(String, String)>,
 }
 
 impl Display for TaskItem {
@@ -97,7 +337,34 @@ impl Display for TaskItem {
 
 impl TaskItem {
     pub fn new(name: String, data: String, status: bool) -> Self {
-        Self { name, data, status }
+        let now = clock::now();
+        Self {
+            name,
+            data,
+            status,
+            created_at: Some(now),
+            completed_at: status.then_some(now),
+            ..Default::default()
+        }
+    }
+
+    /// builds an item from its core fields plus the `key = value` lines that
+    /// follow `data` in a `.tl` file, without recording any history
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn from_parts(
+        name: String,
+        status: bool,
+        data: String,
+        fields: &str,
+    ) -> Result<Self, TaskError> {
+        let mut item = Self {
+            name,
+            status,
+            data,
+            ..Default::default()
+        };
+        item.parse_fields(fields)?;
+        Ok(item)
     }
 
     pub fn parse(source: &str, mut offset: usize) -> Result<(TaskItem, ParserOffset), TaskError> {
@@ -114,7 +381,7 @@ impl TaskItem {
                 ParserState::Name => {
                     let (name, off) = TaskItem::parse_name(&source[offset..])?;
                     offset += off;
-                    item.set_name(name.name);
+                    item.name = name.name;
                     tracing::info!("name offset: {offset}");
                     state = ParserState::Status;
                 }
@@ -122,7 +389,7 @@ impl TaskItem {
                 ParserState::Status => {
                     let (status, off) = TaskItem::parse_status(&source[offset..])?;
                     offset += off;
-                    item.set_status(status.status);
+                    item.status = status.status;
                     tracing::info!("status offset: {offset}");
                     state = ParserState::Data;
                 }
@@ -132,8 +399,9 @@ impl TaskItem {
                     offset += off;
                     tracing::info!("data: {}", data.data);
                     tracing::info!("returned offset: {}", offset);
-                    item.set_data(data.data);
-                    if offset >= source.len().saturating_sub(1) {
+                    item.data = data.data;
+                    offset += item.parse_fields(&source[offset..])?;
+                    if source[offset..].trim().is_empty() {
                         return Ok((item, ParserOffset::Eof));
                     }
                     return Ok((item, ParserOffset::Offset(offset)));
@@ -146,11 +414,16 @@ impl TaskItem {
     fn parse_name(source: &str) -> Result<(TaskName, usize), TaskError> {
         tracing::info!("name source: {source}");
         let (mut found_start, mut start_idx) = (false, 0);
-        for (i, ch) in source.chars().enumerate() {
+        let mut escaped = false;
+        for (i, ch) in source.char_indices() {
             if ch == '\n' {
                 continue;
             }
+            if std::mem::take(&mut escaped) {
+                continue;
+            }
             match ch {
+                '\\' if found_start => escaped = true,
                 '[' => {
                     found_start = true;
                     start_idx = i;
@@ -158,10 +431,8 @@ impl TaskItem {
                 ']' => {
                     if found_start {
                         tracing::info!("name: {}", &source[start_idx + 1..i]);
-                        return Ok((
-                            TaskName::new(source[start_idx + 1..i].to_string()),
-                            i + start_idx + 1,
-                        ));
+                        let name = unescape(&source[start_idx + 1..i], &NAME_SPECIAL);
+                        return Ok((TaskName::new(name), i + start_idx + 1));
                     }
                     break;
                 }
@@ -193,11 +464,11 @@ impl TaskItem {
 
         let mut skip_amount = "status".len();
 
-        for (i, ch) in source.chars().enumerate() {
+        for (i, ch) in source.char_indices() {
             if ch == ' ' {
                 continue;
             }
-            buffer = &source[i..i + skip_amount];
+            buffer = source.get(i..i + skip_amount).unwrap_or_default();
             match state {
                 StatusState::Status => {
                     if buffer == "status" {
@@ -215,7 +486,7 @@ impl TaskItem {
                     if buffer == "true" {
                         tracing::info!("status: true");
                         return Ok((TaskStatus::new(true), i + "true".len()));
-                    } else if &source[i..i + "false".len()] == "false" {
+                    } else if source.get(i..i + "false".len()) == Some("false") {
                         tracing::info!("status: false");
                         return Ok((TaskStatus::new(false), i + "false".len()));
                     } else {
@@ -246,13 +517,14 @@ impl TaskItem {
         let mut skip_amount = "data".len();
 
         let mut found_quote = false;
+        let mut escaped = false;
 
-        for (i, ch) in source.chars().enumerate() {
+        for (i, ch) in source.char_indices() {
             if state != DataState::Content && ch == ' ' {
                 continue;
             }
 
-            buffer = &source[i..i + skip_amount];
+            buffer = source.get(i..i + skip_amount).unwrap_or_default();
             match state {
                 DataState::Data => {
                     if buffer == "data" {
@@ -270,6 +542,11 @@ impl TaskItem {
                     if !found_quote && ch == ' ' {
                         continue;
                     }
+                    if found_quote && (escaped || ch == '\\') {
+                        escaped = !escaped;
+                        data_buffer.push(ch);
+                        continue;
+                    }
                     if ch == '\"' {
                         match found_quote {
                             false => {
@@ -279,7 +556,7 @@ impl TaskItem {
                             true => {
                                 let data = data_buffer.iter().collect::<String>();
                                 tracing::info!("data: {data}");
-                                let data = TaskData::new(data_buffer.iter().collect::<String>());
+                                let data = TaskData::new(unescape(&data, &DATA_SPECIAL));
                                 return Ok((data, i + 1));
                             }
                         }
@@ -289,12 +566,6 @@ impl TaskItem {
                             "data segment does not start with a `\"`".to_string(),
                         ));
                     }
-                    if (ch == '[' || ch == ']') && !data_buffer.is_empty() && found_quote {
-                        return Err(TaskError::ParseError(
-                            "data segment does not end with a `\"`".to_string(),
-                        ));
-                    }
-
                     data_buffer.push(ch);
                 }
             }
@@ -303,6 +574,324 @@ impl TaskItem {
         Err(TaskError::ParseError("failed to parse data".to_string()))
     }
 
+    /// parses the optional `key = value` lines after `data`, up to the next
+    /// `[name]` header, and returns how many bytes were consumed
+    fn parse_fields(&mut self, source: &str) -> Result<usize, TaskError> {
+        let mut consumed = 0;
+        for line in source.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') {
+                break;
+            }
+            if !trimmed.is_empty() {
+                let Some((key, value)) = trimmed.split_once('=') else {
+                    return Err(TaskError::ParseError(format!(
+                        "expected `key = value`, found {trimmed}"
+                    )));
+                };
+                self.set_field(key.trim(), value.trim())?;
+            }
+            consumed += line.len();
+        }
+        Ok(consumed)
+    }
+
+    fn set_field(&mut self, key: &str, value: &str) -> Result<(), TaskError> {
+        match key {
+            "created" => self.created_at = Some(parse_timestamp(key, value)?),
+            "completed" => self.completed_at = Some(parse_timestamp(key, value)?),
+            "history" => self.history.push(TaskChange::parse(unquote(value))?),
+            "repeat" => self.repeat = Some(Repeat::parse(unquote(value))?),
+            "session" => self.sessions.push(Session::parse(unquote(value))?),
+            "pomodoros" => {
+                self.pomodoros = value.parse().map_err(|_| {
+                    TaskError::ParseError(format!("pomodoros must be a number, found {value}"))
+                })?
+            }
+            "estimate" => {
+                self.estimate = Some(value.parse().map_err(|_| {
+                    TaskError::ParseError(format!("estimate must be seconds, found {value}"))
+                })?)
+            }
+            "planned" => self.planned = Some(parse_date(key, value)?),
+            "due" => self.due = Some(parse_date(key, value)?),
+            "wait" => self.wait = Some(parse_date(key, value)?),
+            "priority" => self.priority = Some(Priority::parse(unquote(value))?),
+            "tags" => {
+                self.tags = unquote(value)
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "blocked" => {
+                self.blocked = value.parse().map_err(|_| {
+                    TaskError::ParseError(format!("blocked must be true or false, found {value}"))
+                })?
+            }
+            _ => self.extra.push((key.to_string(), value.to_string())),
+        }
+        Ok(())
+    }
+
+    /// writes the item in the same `.tl` format that `TaskItem::parse` reads
+    pub fn serialize(&self, mut w: impl Write) -> std::io::Result<()> {
+        self.serialize_name(&mut w)?;
+        writeln!(w, "status = {}", self.status())?;
+        self.serialize_data(&mut w)?;
+        self.serialize_fields(w)
+    }
+
+    /// writes the `[name]` header
+    pub(crate) fn serialize_name(&self, mut w: impl Write) -> std::io::Result<()> {
+        writeln!(w, "[{}]", escape(self.name(), &NAME_SPECIAL))
+    }
+
+    /// writes the `data` line, which spans several lines when `data` does
+    pub(crate) fn serialize_data(&self, mut w: impl Write) -> std::io::Result<()> {
+        writeln!(w, "data = \"{}\"", escape(self.data(), &DATA_SPECIAL))
+    }
+
+    /// writes everything after `data`
+    pub(crate) fn serialize_fields(&self, mut w: impl Write) -> std::io::Result<()> {
+        if let Some(created) = self.created_at {
+            writeln!(w, "created = {created}")?;
+        }
+        if let Some(completed) = self.completed_at {
+            writeln!(w, "completed = {completed}")?;
+        }
+        for change in &self.history {
+            writeln!(w, "history = \"{change}\"")?;
+        }
+        if let Some(repeat) = self.repeat {
+            writeln!(w, "repeat = \"{repeat}\"")?;
+        }
+        for session in &self.sessions {
+            writeln!(w, "session = \"{session}\"")?;
+        }
+        if self.pomodoros > 0 {
+            writeln!(w, "pomodoros = {}", self.pomodoros)?;
+        }
+        if let Some(estimate) = self.estimate {
+            writeln!(w, "estimate = {estimate}")?;
+        }
+        if let Some(planned) = self.planned {
+            writeln!(w, "planned = \"{}\"", clock::format_date(planned))?;
+        }
+        if let Some(due) = self.due {
+            writeln!(w, "due = \"{}\"", clock::format_date(due))?;
+        }
+        if let Some(priority) = self.priority {
+            writeln!(w, "priority = \"{priority}\"")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(w, "tags = \"{}\"", self.tags.join(","))?;
+        }
+        if self.blocked {
+            writeln!(w, "blocked = true")?;
+        }
+        if let Some(wait) = self.wait {
+            writeln!(w, "wait = \"{}\"", clock::format_date(wait))?;
+        }
+        for (key, value) in &self.extra {
+            writeln!(w, "{key} = {value}")?;
+        }
+        Ok(())
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// when the running timer was started, if there is one
+    pub fn running_since(&self) -> Option<u64> {
+        self.sessions
+            .last()
+            .filter(|session| session.end.is_none())
+            .map(|session| session.start)
+    }
+
+    /// returns false if the timer was already running
+    pub fn start_timer(&mut self, now: u64) -> bool {
+        if self.running_since().is_some() {
+            return false;
+        }
+        self.sessions.push(Session {
+            start: now,
+            end: None,
+        });
+        true
+    }
+
+    /// stops the running timer and returns how long the session lasted
+    pub fn stop_timer(&mut self, now: u64) -> Option<u64> {
+        let session = self.sessions.last_mut().filter(|s| s.end.is_none())?;
+        session.end = Some(now.max(session.start));
+        Some(session.duration(now))
+    }
+
+    /// total seconds tracked on this task
+    pub fn tracked(&self, now: u64) -> u64 {
+        self.sessions.iter().map(|s| s.duration(now)).sum()
+    }
+
+    /// seconds tracked on this task during `day`
+    pub fn tracked_on(&self, day: i64, now: u64) -> u64 {
+        self.sessions.iter().map(|s| s.duration_on(day, now)).sum()
+    }
+
+    /// finished focus sessions
+    pub fn pomodoros(&self) -> usize {
+        self.pomodoros
+    }
+
+    pub fn add_pomodoro(&mut self) {
+        self.pomodoros += 1;
+        self.record("pomodoros", self.pomodoros.to_string());
+    }
+
+    pub fn estimate(&self) -> Option<u64> {
+        self.estimate
+    }
+
+    pub fn set_estimate(&mut self, estimate: Option<u64>) {
+        if self.estimate != estimate {
+            let value = estimate.map(|e| e.to_string()).unwrap_or_default();
+            self.record("estimate", value);
+        }
+        self.estimate = estimate
+    }
+
+    pub fn planned(&self) -> Option<i64> {
+        self.planned
+    }
+
+    pub fn set_planned(&mut self, planned: Option<i64>) {
+        if self.planned != planned {
+            let value = planned.map(clock::format_date).unwrap_or_default();
+            self.record("planned", value);
+        }
+        self.planned = planned
+    }
+
+    pub fn due(&self) -> Option<i64> {
+        self.due
+    }
+
+    pub fn set_due(&mut self, due: Option<i64>) {
+        if self.due != due {
+            self.record("due", due.map(clock::format_date).unwrap_or_default());
+        }
+        self.due = due
+    }
+
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        if self.priority != priority {
+            let value = priority.map(|p| p.to_string()).unwrap_or_default();
+            self.record("priority", value);
+        }
+        self.priority = priority
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        if self.tags != tags {
+            self.record("tags", tags.join(","));
+        }
+        self.tags = tags
+    }
+
+    pub fn blocked(&self) -> bool {
+        self.blocked
+    }
+
+    pub fn set_blocked(&mut self, blocked: bool) {
+        if self.blocked != blocked {
+            self.record("blocked", blocked.to_string());
+        }
+        self.blocked = blocked
+    }
+
+    pub fn wait(&self) -> Option<i64> {
+        self.wait
+    }
+
+    pub fn set_wait(&mut self, wait: Option<i64>) {
+        if self.wait != wait {
+            self.record("wait", wait.map(clock::format_date).unwrap_or_default());
+        }
+        self.wait = wait
+    }
+
+    /// deferred until a day after `today`
+    pub fn is_waiting(&self, today: i64) -> bool {
+        self.wait.is_some_and(|wait| wait > today)
+    }
+
+    pub fn repeat(&self) -> Option<Repeat> {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: Option<Repeat>) {
+        if self.repeat != repeat {
+            let value = repeat.map(|r| r.to_string()).unwrap_or_default();
+            self.record("repeat", value);
+        }
+        self.repeat = repeat
+    }
+
+    /// every time this task was marked as done, oldest first
+    pub fn completions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.history
+            .iter()
+            .filter(|change| change.field == "status" && change.value == "true")
+            .map(|change| change.at)
+    }
+
+    /// a repeating task that was finished in an earlier period is open again,
+    /// returns true if the status was reset
+    pub fn roll_over(&mut self, today: i64) -> bool {
+        let (Some(repeat), Some(completed)) = (self.repeat, self.completed_at) else {
+            return false;
+        };
+        if !self.status || repeat.period(clock::day(completed)) >= repeat.period(today) {
+            return false;
+        }
+        // not a change the user made, so it stays out of the history
+        self.status = false;
+        self.completed_at = None;
+        true
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    pub fn completed_at(&self) -> Option<u64> {
+        self.completed_at
+    }
+
+    /// every recorded change to this task, oldest first
+    pub fn history(&self) -> &[TaskChange] {
+        &self.history
+    }
+
+    pub fn record(&mut self, field: &str, value: String) {
+        self.history.push(TaskChange {
+            at: clock::now(),
+            field: field.to_string(),
+            value,
+        });
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -316,22 +905,60 @@ impl TaskItem {
     }
 
     pub fn set_name(&mut self, name: String) {
+        if self.name != name {
+            self.record("name", name.clone());
+        }
         self.name = name
     }
 
     pub fn set_data(&mut self, data: String) {
+        if self.data != data {
+            self.record("data", String::new());
+        }
         self.data = data
     }
 
     pub fn set_status(&mut self, status: bool) {
+        if self.status == status {
+            return;
+        }
+        self.record("status", status.to_string());
+        self.completed_at = status.then(clock::now);
         self.status = status
     }
 }
 
-#[derive(Default, Debug, Clone)]
+/// Sent to every subscriber of a `TaskList` after it changes
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+    Added(usize, TaskItem),
+    Removed(usize, TaskItem),
+    Updated(usize, TaskItem),
+    Moved {
+        from: usize,
+        to: usize,
+    },
+    /// every task was swapped out at once, e.g. after a reload
+    Reset(Vec<TaskItem>),
+}
+
+#[derive(Default, Debug)]
 pub struct TaskList {
     pub source: String,
-    pub list: Vec<TaskItem>,
+    list: Vec<TaskItem>,
+    subscribers: Vec<Sender<TaskEvent>>,
+}
+
+/// clones don't inherit subscribers, they'd otherwise hear about changes to
+/// a list they are not watching
+impl Clone for TaskList {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            list: self.list.clone(),
+            subscribers: vec![],
+        }
+    }
 }
 
 impl TaskList {
@@ -364,14 +991,28 @@ impl TaskList {
         }
     }
 
-    pub fn iter(&self) -> TaskIter {
+    /// returns a receiver for every change made to this list from now on,
+    /// dropping the receiver unsubscribes
+    pub fn subscribe(&mut self) -> Receiver<TaskEvent> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    fn notify(&mut self, event: TaskEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn iter(&self) -> TaskIter<'_> {
         TaskIter { list: self, idx: 0 }
     }
 
-    pub fn serialize(&self, mut w: impl Write) {
-        self.iter().for_each(|item| {
-            write!(w, "{item}").unwrap();
-        });
+    pub fn serialize(&self, mut w: impl Write) -> std::io::Result<()> {
+        for item in self.iter() {
+            item.serialize(&mut w)?;
+        }
+        Ok(())
     }
 
     pub fn deserialize(source: String) -> Result<Self, TaskError> {
@@ -382,24 +1023,138 @@ impl TaskList {
         self.list.is_empty()
     }
 
-    pub fn get(&self, idx: usize) -> Option<&TaskItem> {
-        self.list.get(idx)
+    pub fn len(&self) -> usize {
+        self.list.len()
     }
 
-    pub fn get_mut(&mut self, idx: usize) -> Option<&mut TaskItem> {
-        self.list.get_mut(idx)
+    pub fn get(&self, idx: usize) -> Option<&TaskItem> {
+        self.list.get(idx)
     }
 
     pub fn push(&mut self, item: TaskItem) {
-        self.list.push(item)
+        self.list.push(item.clone());
+        self.notify(TaskEvent::Added(self.list.len() - 1, item));
     }
 
     pub fn set(&mut self, idx: usize, task: TaskItem) {
-        self.list.insert(idx, task)
+        self.list.insert(idx, task.clone());
+        self.notify(TaskEvent::Added(idx, task));
     }
 
     pub fn remove(&mut self, idx: usize) {
-        self.list.remove(idx);
+        let item = self.list.remove(idx);
+        self.notify(TaskEvent::Removed(idx, item));
+    }
+
+    /// replaces the task at `idx` and returns the old one
+    pub fn replace(&mut self, idx: usize, task: TaskItem) -> Option<TaskItem> {
+        let old = std::mem::replace(self.list.get_mut(idx)?, task.clone());
+        self.notify(TaskEvent::Updated(idx, task));
+        Some(old)
+    }
+
+    /// swaps in the tasks of `other`, subscribers stay subscribed
+    pub fn reset(&mut self, other: TaskList) {
+        self.source = other.source;
+        self.list = other.list;
+        self.notify(TaskEvent::Reset(self.list.clone()));
+    }
+
+    /// applies an event received from another list, so a subscriber can keep
+    /// a copy of that list up to date
+    pub fn apply(&mut self, event: TaskEvent) {
+        match event {
+            TaskEvent::Added(idx, item) if idx <= self.list.len() => self.set(idx, item),
+            TaskEvent::Removed(idx, _) if idx < self.list.len() => self.remove(idx),
+            TaskEvent::Updated(idx, item) => {
+                self.replace(idx, item);
+            }
+            TaskEvent::Moved { from, to } => {
+                self.move_item(from, to);
+            }
+            TaskEvent::Reset(list) => {
+                self.list = list.clone();
+                self.notify(TaskEvent::Reset(list));
+            }
+            _ => tracing::error!("task event out of bounds: {event:?}"),
+        }
+    }
+
+    /// moves the task at `from` so it ends up at index `to`
+    pub fn move_item(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.list.len() || to >= self.list.len() {
+            return false;
+        }
+        let item = self.list.remove(from);
+        self.list.insert(to, item);
+        self.notify(TaskEvent::Moved { from, to });
+        true
+    }
+
+    /// every change recorded in `from..to`, oldest first
+    pub fn changes_between(&self, from: u64, to: u64) -> Vec<(&TaskItem, &TaskChange)> {
+        let mut changes: Vec<_> = self
+            .list
+            .iter()
+            .flat_map(|item| item.history().iter().map(move |change| (item, change)))
+            .filter(|(_, change)| (from..to).contains(&change.at))
+            .collect();
+        changes.sort_by_key(|(_, change)| change.at);
+        changes
+    }
+
+    /// indices of the tasks deferred past `today`, soonest first
+    pub fn waiting(&self, today: i64) -> Vec<usize> {
+        let mut waiting: Vec<usize> = (0..self.list.len())
+            .filter(|index| self.list[*index].is_waiting(today))
+            .collect();
+        waiting.sort_by_key(|index| self.list[*index].wait);
+        waiting
+    }
+
+    /// the index of the task whose timer is running
+    pub fn running(&self) -> Option<usize> {
+        self.list
+            .iter()
+            .position(|item| item.running_since().is_some())
+    }
+
+    /// seconds tracked across every task during `day`
+    pub fn tracked_on(&self, day: i64, now: u64) -> u64 {
+        self.list.iter().map(|item| item.tracked_on(day, now)).sum()
+    }
+
+    /// reopens every repeating task whose period has passed, returns the
+    /// indices that changed
+    pub fn roll_over(&mut self, today: i64) -> Vec<usize> {
+        let mut changed = vec![];
+        for idx in 0..self.list.len() {
+            let mut item = self.list[idx].clone();
+            if item.roll_over(today) {
+                self.replace(idx, item);
+                changed.push(idx);
+            }
+        }
+        changed
+    }
+
+    /// tasks that are done and were finished in `from..to`
+    pub fn completed_between(&self, from: u64, to: u64) -> Vec<&TaskItem> {
+        self.list
+            .iter()
+            .filter(|item| {
+                item.completed_at()
+                    .is_some_and(|at| (from..to).contains(&at))
+            })
+            .collect()
+    }
+}
+
+impl Index<usize> for TaskList {
+    type Output = TaskItem;
+
+    fn index(&self, idx: usize) -> &TaskItem {
+        &self.list[idx]
     }
 }
 
@@ -418,7 +1173,7 @@ mod test {
 
     use tracing_subscriber::FmtSubscriber;
 
-    use super::TaskList;
+    use super::{TaskEvent, TaskItem, TaskList};
 
     #[test]
     pub fn test_parser() {
@@ -453,4 +1208,175 @@ data = "urmom"
         println!("{parser}");
         assert!(parser.list.len() == 3);
     }
+
+    #[test]
+    pub fn test_escaping_round_trip() {
+        let names = ["x]y", "[a] b", "back\\", "a\\]"];
+        let data = [
+            "say \"hi\" there",
+            "see [link]",
+            "a\\nb",
+            "ends with \\",
+            "\\\"",
+            "two\nlines",
+        ];
+
+        let mut list = TaskList::new();
+        for (name, data) in names.iter().zip(data.iter().cycle()) {
+            list.push(TaskItem::new(name.to_string(), data.to_string(), false));
+        }
+        for data in data {
+            list.push(TaskItem::new("plain".into(), data.to_string(), true));
+        }
+        let mut source = vec![];
+        list.serialize(&mut source).unwrap();
+        let source = String::from_utf8(source).unwrap();
+        let parsed = TaskList::deserialize(source.clone()).unwrap();
+
+        assert_eq!(parsed.len(), list.len(), "{source}");
+        for (parsed, item) in parsed.iter().zip(list.iter()) {
+            assert_eq!(parsed.name(), item.name(), "{source}");
+            assert_eq!(parsed.data(), item.data(), "{source}");
+        }
+        // text that doesn't need escaping is written as is
+        assert!(source.contains("data = \"a\\nb\""));
+    }
+
+    #[test]
+    pub fn test_subscribe() {
+        let mut list = TaskList::new();
+        let events = list.subscribe();
+
+        list.push(TaskItem::new("a".into(), "".into(), false));
+        list.push(TaskItem::new("b".into(), "".into(), false));
+        list.replace(0, TaskItem::new("a".into(), "".into(), true));
+        list.move_item(1, 0);
+        list.remove(0);
+
+        // clones don't report back to our subscribers
+        list.clone().push(TaskItem::default());
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert_eq!(events.len(), 5);
+        // a copy kept up to date from the events ends up the same
+        let mut copy = TaskList::new();
+        events.iter().for_each(|event| copy.apply(event.clone()));
+        assert_eq!(copy.to_string(), list.to_string());
+        assert!(matches!(&events[0], TaskEvent::Added(0, item) if item.name() == "a"));
+        assert!(matches!(&events[2], TaskEvent::Updated(0, item) if item.status()));
+        assert!(matches!(events[3], TaskEvent::Moved { from: 1, to: 0 }));
+        assert!(matches!(&events[4], TaskEvent::Removed(0, item) if item.name() == "b"));
+
+        let events = list.subscribe();
+        list.reset(TaskList::deserialize("[c]\nstatus = false\ndata = \"\"\n".into()).unwrap());
+        copy.apply(events.try_recv().unwrap());
+        assert_eq!(copy.len(), 1);
+        assert_eq!(copy[0].name(), "c");
+    }
+
+    #[test]
+    pub fn test_history_round_trip() {
+        let mut item = TaskItem::new("a".into(), "".into(), false);
+        assert!(item.created_at().is_some());
+        item.set_status(true);
+        item.set_data("more".into());
+        item.set_status(true);
+        assert!(item.completed_at().is_some());
+        assert_eq!(item.history().len(), 2);
+
+        let mut list = TaskList::new();
+        list.push(item);
+        let mut source = vec![];
+        list.serialize(&mut source).unwrap();
+        let list = TaskList::deserialize(String::from_utf8(source).unwrap()).unwrap();
+
+        let item = list.get(0).unwrap();
+        assert_eq!(item.history()[0].field, "status");
+        assert_eq!(item.history()[0].value, "true");
+        assert_eq!(item.history()[1].field, "data");
+        assert_eq!(list.changes_between(0, u64::MAX).len(), 2);
+        assert_eq!(list.completed_between(0, u64::MAX).len(), 1);
+        assert!(list.completed_between(0, 1).is_empty());
+    }
+
+    #[test]
+    pub fn test_unknown_fields_are_kept() {
+        let source =
+            "[a]\nstatus = false\ndata = \"\"\nfuture = \"x y\"\n[b]\nstatus = true\ndata = \"\"\n";
+        let list = TaskList::deserialize(source.to_string()).unwrap();
+        assert_eq!(list.list.len(), 2);
+
+        let mut out = vec![];
+        list.serialize(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), source);
+    }
+
+    #[test]
+    pub fn test_urgency_fields() {
+        let source = "[a]\nstatus = false\ndata = \"\"\ndue = \"2024-02-29\"\npriority = \"h\"\ntags = \"work, home\"\nblocked = true\n";
+        let list = TaskList::deserialize(source.to_string()).unwrap();
+        let item = list.get(0).unwrap();
+        assert_eq!(item.due(), Some(19782));
+        assert_eq!(item.priority(), Some(super::Priority::High));
+        assert_eq!(item.tags(), ["work", "home"]);
+        assert!(item.blocked());
+
+        let mut out = vec![];
+        list.serialize(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            source
+                .replace("\"h\"", "\"high\"")
+                .replace("work, home", "work,home")
+        );
+        assert!(
+            TaskList::deserialize("[a]\nstatus = false\ndata = \"\"\ndue = \"soon\"\n".into())
+                .is_err()
+        );
+    }
+
+    #[test]
+    pub fn test_waiting() {
+        let source = "[a]\nstatus = false\ndata = \"\"\nwait = \"1970-01-11\"\n[b]\nstatus = false\ndata = \"\"\nwait = \"1970-01-05\"\n[c]\nstatus = false\ndata = \"\"\n";
+        let list = TaskList::deserialize(source.to_string()).unwrap();
+        assert_eq!(list.waiting(0), [1, 0]);
+        assert_eq!(list.waiting(5), [0]);
+        assert!(!list.get(2).unwrap().is_waiting(0));
+
+        let mut out = vec![];
+        list.serialize(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), source);
+    }
+
+    #[test]
+    pub fn test_timers() {
+        use crate::clock::DAY;
+
+        let mut item = TaskItem::new("a".into(), "".into(), false);
+        assert!(item.start_timer(DAY - 60));
+        assert!(!item.start_timer(DAY));
+        assert_eq!(item.running_since(), Some(DAY - 60));
+        // a session running across midnight is split between the days
+        assert_eq!(item.tracked_on(1, DAY + 30), 30);
+        assert_eq!(item.stop_timer(DAY + 120), Some(180));
+        assert_eq!(item.stop_timer(DAY + 200), None);
+        item.start_timer(2 * DAY);
+        item.add_pomodoro();
+        item.set_estimate(Some(3600));
+        item.set_planned(Some(3));
+
+        let mut list = TaskList::new();
+        list.push(item);
+        let mut source = vec![];
+        list.serialize(&mut source).unwrap();
+        let list = TaskList::deserialize(String::from_utf8(source).unwrap()).unwrap();
+
+        assert_eq!(list.running(), Some(0));
+        assert_eq!(list.get(0).unwrap().pomodoros(), 1);
+        assert_eq!(list.get(0).unwrap().estimate(), Some(3600));
+        assert_eq!(list.get(0).unwrap().planned(), Some(3));
+        assert_eq!(list.get(0).unwrap().tracked(2 * DAY + 10), 190);
+        assert_eq!(list.tracked_on(0, 2 * DAY + 10), 60);
+        assert_eq!(list.tracked_on(2, 2 * DAY + 10), 10);
+    }
 }
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::{
+    clock,
+    tasks::{Priority, TaskItem, TaskList},
+};
+
+/// Weights for each part of the urgency score. A task's urgency is the sum of
+/// every coefficient times how strongly that part applies to it, between 0.0
+/// and 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrgencyConfig {
+    /// scaled from 0.2 two weeks before the due date up to 1.0 a week after
+    pub due: f64,
+    pub priority_high: f64,
+    pub priority_medium: f64,
+    pub priority_low: f64,
+    /// grows with the task's age until it is `max_age` days old
+    pub age: f64,
+    pub max_age: f64,
+    /// applies to every task with at least one tag
+    pub tagged: f64,
+    /// extra weight for specific tags
+    pub tags: HashMap<String, f64>,
+    pub blocked: f64,
+    pub done: f64,
+}
+
+impl Default for UrgencyConfig {
+    fn default() -> Self {
+        Self {
+            due: 12.0,
+            priority_high: 6.0,
+            priority_medium: 3.9,
+            priority_low: 1.8,
+            age: 2.0,
+            max_age: 365.0,
+            tagged: 1.0,
+            tags: HashMap::new(),
+            blocked: -5.0,
+            done: -20.0,
+        }
+    }
+}
+
+impl UrgencyConfig {
+    pub fn urgency(&self, item: &TaskItem, now: u64) -> f64 {
+        let today = clock::day(now);
+        let mut urgency = 0.0;
+
+        if let Some(due) = item.due() {
+            let overdue = (today - due) as f64;
+            let due = match overdue {
+                o if o >= 7.0 => 1.0,
+                o if o >= -14.0 => (o + 14.0) * 0.8 / 21.0 + 0.2,
+                _ => 0.2,
+            };
+            urgency += self.due * due;
+        }
+
+        urgency += match item.priority() {
+            Some(Priority::High) => self.priority_high,
+            Some(Priority::Medium) => self.priority_medium,
+            Some(Priority::Low) => self.priority_low,
+            None => 0.0,
+        };
+
+        if let Some(created) = item.created_at() {
+            let age = now.saturating_sub(created) as f64 / clock::DAY as f64;
+            if self.max_age > 0.0 {
+                urgency += self.age * (age / self.max_age).min(1.0);
+            }
+        }
+
+        if !item.tags().is_empty() {
+            urgency += self.tagged;
+        }
+        urgency += item
+            .tags()
+            .iter()
+            .filter_map(|tag| self.tags.get(tag))
+            .sum::<f64>();
+
+        if item.blocked() {
+            urgency += self.blocked;
+        }
+        if item.status() {
+            urgency += self.done;
+        }
+        urgency
+    }
+
+    /// indices into `list`, most urgent first. Tasks with the same urgency keep
+    /// their file order.
+    pub fn sort(&self, list: &TaskList, now: u64) -> Vec<usize> {
+        let urgency: Vec<f64> = list.iter().map(|item| self.urgency(&item, now)).collect();
+        let mut order: Vec<usize> = (0..urgency.len()).collect();
+        order.sort_by(|a, b| urgency[*b].total_cmp(&urgency[*a]));
+        order
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UrgencyConfig;
+    use crate::{
+        clock::DAY,
+        tasks::{Priority, TaskItem, TaskList},
+    };
+
+    #[test]
+    pub fn test_urgency_order() {
+        let now = 100 * DAY;
+        let task = |name: &str| {
+            let source = format!("[{name}]\nstatus = false\ndata = \"\"\ncreated = {now}\n");
+            TaskList::deserialize(source).unwrap()[0].clone()
+        };
+
+        let plain = task("plain");
+        let mut overdue = task("overdue");
+        overdue.set_due(Some(90));
+        let mut high = task("high");
+        high.set_priority(Some(Priority::High));
+        let mut blocked = task("blocked");
+        blocked.set_priority(Some(Priority::High));
+        blocked.set_blocked(true);
+        let mut done = task("done");
+        done.set_status(true);
+        let mut tagged = task("tagged");
+        tagged.set_tags(vec!["work".into()]);
+
+        let mut list = TaskList::new();
+        [plain, overdue, high, blocked, done, tagged]
+            .into_iter()
+            .for_each(|item| list.push(item));
+
+        let config = UrgencyConfig::default();
+        assert_eq!(config.urgency(list.get(1).unwrap(), now), 12.0);
+        let names = |order: Vec<usize>| -> Vec<String> {
+            order
+                .into_iter()
+                .map(|i| list.get(i).unwrap().name().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(config.sort(&list, now)),
+            ["overdue", "high", "blocked", "tagged", "plain", "done"]
+        );
+
+        let mut config = UrgencyConfig::default();
+        config.tags.insert("work".into(), 20.0);
+        assert_eq!(names(config.sort(&list, now))[0], "tagged");
+        let old = TaskItem::new("old".into(), "".into(), false);
+        assert!(config.urgency(&old, old.created_at().unwrap() + 365 * DAY) >= 2.0);
+    }
+}
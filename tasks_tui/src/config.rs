@@ -0,0 +1,399 @@
+//! `$XDG_CONFIG_HOME/tasks/config.toml`, every setting is optional.
+//!
+//! ```toml
+//! file = "~/notes/tasks.tl"
+//! log_level = "info"
+//! mouse = true
+//! sort = "urgency"      # or "file"
+//! filter = "active"     # or "waiting"
+//! capacity = "6h"
+//! autosave = "2s"       # or "off"
+//!
+//! [pomodoro]
+//! work = "25m"
+//! break = "5m"
+//!
+//! [theme]
+//! background = "#282727"
+//! border = "#5c6370"
+//!
+//! [keys]
+//! undo = "u"
+//! redo = "ctrl+r"
+//!
+//! [urgency]
+//! due = 12.0
+//! tags = { work = 2.0 }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tasks_core::{clock, planning::DEFAULT_CAPACITY, pomodoro::Pomodoro, urgency::UrgencyConfig};
+
+use crate::{
+    keys::Keymap,
+    selection::{Filter, Sort},
+};
+
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    file: Option<PathBuf>,
+    log_level: Option<String>,
+    mouse: Option<bool>,
+    sort: Option<String>,
+    filter: Option<String>,
+    capacity: Option<String>,
+    autosave: Option<String>,
+    #[serde(default)]
+    pomodoro: RawPomodoro,
+    #[serde(default)]
+    theme: RawTheme,
+    #[serde(default)]
+    keys: HashMap<String, String>,
+    #[serde(default)]
+    urgency: RawUrgency,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPomodoro {
+    work: Option<String>,
+    #[serde(rename = "break")]
+    rest: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTheme {
+    background: Option<String>,
+    accent: Option<String>,
+    heatmap: Option<String>,
+    warning: Option<String>,
+    border: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawUrgency {
+    due: Option<f64>,
+    priority_high: Option<f64>,
+    priority_medium: Option<f64>,
+    priority_low: Option<f64>,
+    age: Option<f64>,
+    max_age: Option<f64>,
+    tagged: Option<f64>,
+    #[serde(default)]
+    tags: HashMap<String, f64>,
+    blocked: Option<f64>,
+    done: Option<f64>,
+}
+
+/// `#rrggbb` colors the templates are drawn with
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub background: String,
+    /// the running timer and the focused pane
+    pub accent: String,
+    pub heatmap: String,
+    /// overbooked days
+    pub warning: String,
+    /// panes without focus
+    pub border: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: "#282727".into(),
+            accent: "#e5c07b".into(),
+            heatmap: "#39d353".into(),
+            warning: "#e06c75".into(),
+            border: "#5c6370".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// the task file used when none is passed on the command line
+    pub file: Option<PathBuf>,
+    pub log_level: tracing::Level,
+    pub mouse: bool,
+    pub sort: Sort,
+    pub filter: Filter,
+    /// seconds of work planned per day
+    pub capacity: u64,
+    /// how long to wait after the last key press before saving, `None` only
+    /// saves on request
+    pub autosave: Option<Duration>,
+    pub pomodoro_work: Duration,
+    pub pomodoro_break: Duration,
+    pub theme: Theme,
+    pub keys: Keymap,
+    pub urgency: UrgencyConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            file: None,
+            log_level: tracing::Level::INFO,
+            mouse: true,
+            sort: Sort::File,
+            filter: Filter::Active,
+            capacity: DEFAULT_CAPACITY,
+            autosave: Some(Duration::from_secs(2)),
+            pomodoro_work: Pomodoro::WORK,
+            pomodoro_break: Pomodoro::BREAK,
+            theme: Theme::default(),
+            keys: Keymap::default(),
+            urgency: UrgencyConfig::default(),
+        }
+    }
+}
+
+fn color(key: &str, value: Option<String>, default: String) -> Result<String, String> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    let hex = value.strip_prefix('#').unwrap_or_default();
+    match hex.len() == 6 && hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        true => Ok(value),
+        false => Err(format!(
+            "theme.{key} must be a color like \"#282727\", found {value:?}"
+        )),
+    }
+}
+
+fn duration(key: &str, value: Option<String>, default: u64) -> Result<u64, String> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    match clock::parse_duration(&value) {
+        Some(0) | None => Err(format!(
+            "{key} must be a duration like \"1h30m\" or \"25m\", found {value:?}"
+        )),
+        Some(seconds) => Ok(seconds),
+    }
+}
+
+/// `~/` is expanded, relative paths are taken from the config directory
+fn expand(file: PathBuf, dir: &Path) -> PathBuf {
+    if let (Ok(rest), Some(home)) = (file.strip_prefix("~"), std::env::var_os("HOME")) {
+        return Path::new(&home).join(rest);
+    }
+    dir.join(file)
+}
+
+impl Config {
+    /// loads the config from `path`, a missing file gives the defaults
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let err = |message: String| ConfigError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(err(e.to_string())),
+        };
+        let raw: RawConfig = toml::from_str(&source).map_err(|e| err(e.to_string()))?;
+        Config::validate(raw, path.parent().unwrap_or(Path::new("."))).map_err(err)
+    }
+
+    fn validate(raw: RawConfig, dir: &Path) -> Result<Self, String> {
+        let default = Config::default();
+
+        let log_level = match raw.log_level.as_deref() {
+            None => default.log_level,
+            Some(level) => level.parse().map_err(|_| {
+                format!(
+                    "log_level must be one of trace, debug, info, warn or error, found {level:?}"
+                )
+            })?,
+        };
+        let sort = match raw.sort.as_deref() {
+            None => default.sort,
+            Some("file") => Sort::File,
+            Some("urgency") => Sort::Urgency,
+            Some(sort) => return Err(format!("sort must be file or urgency, found {sort:?}")),
+        };
+        let filter = match raw.filter.as_deref() {
+            None => default.filter,
+            Some("active") => Filter::Active,
+            Some("waiting") => Filter::Waiting,
+            Some(filter) => {
+                return Err(format!(
+                    "filter must be active or waiting, found {filter:?}"
+                ))
+            }
+        };
+
+        let autosave = match raw.autosave.as_deref() {
+            Some("off") => None,
+            Some(_) => Some(Duration::from_secs(duration("autosave", raw.autosave, 0)?)),
+            None => default.autosave,
+        };
+
+        let theme = Theme {
+            background: color("background", raw.theme.background, default.theme.background)?,
+            accent: color("accent", raw.theme.accent, default.theme.accent)?,
+            heatmap: color("heatmap", raw.theme.heatmap, default.theme.heatmap)?,
+            warning: color("warning", raw.theme.warning, default.theme.warning)?,
+            border: color("border", raw.theme.border, default.theme.border)?,
+        };
+
+        let keys = Keymap::with_overrides(&raw.keys).map_err(|e| format!("keys: {e}"))?;
+
+        let u = raw.urgency;
+        let d = default.urgency;
+        let urgency = UrgencyConfig {
+            due: u.due.unwrap_or(d.due),
+            priority_high: u.priority_high.unwrap_or(d.priority_high),
+            priority_medium: u.priority_medium.unwrap_or(d.priority_medium),
+            priority_low: u.priority_low.unwrap_or(d.priority_low),
+            age: u.age.unwrap_or(d.age),
+            max_age: u.max_age.unwrap_or(d.max_age),
+            tagged: u.tagged.unwrap_or(d.tagged),
+            tags: u.tags,
+            blocked: u.blocked.unwrap_or(d.blocked),
+            done: u.done.unwrap_or(d.done),
+        };
+        if urgency.max_age <= 0.0 {
+            return Err("urgency.max_age must be more than 0 days".to_string());
+        }
+
+        Ok(Self {
+            file: raw.file.map(|file| expand(file, dir)),
+            log_level,
+            mouse: raw.mouse.unwrap_or(default.mouse),
+            sort,
+            filter,
+            capacity: duration("capacity", raw.capacity, default.capacity)?,
+            autosave,
+            pomodoro_work: Duration::from_secs(duration(
+                "pomodoro.work",
+                raw.pomodoro.work,
+                default.pomodoro_work.as_secs(),
+            )?),
+            pomodoro_break: Duration::from_secs(duration(
+                "pomodoro.break",
+                raw.pomodoro.rest,
+                default.pomodoro_break.as_secs(),
+            )?),
+            theme,
+            keys,
+            urgency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::Path, time::Duration};
+
+    use super::{Config, RawConfig};
+    use crate::keys::{Action, KeyBinding};
+
+    fn parse(source: &str) -> Result<Config, String> {
+        let raw: RawConfig = toml::from_str(source).map_err(|e| e.to_string())?;
+        Config::validate(raw, Path::new("/config"))
+    }
+
+    #[test]
+    pub fn test_defaults() {
+        let config = parse("").unwrap();
+        assert_eq!(config.autosave, Some(Duration::from_secs(2)));
+        assert_eq!(config.theme.accent, "#e5c07b");
+
+        let missing = std::env::temp_dir().join("tasks_config_missing.toml");
+        let config = Config::load(&missing).unwrap();
+        assert_eq!(config.capacity, Config::default().capacity);
+    }
+
+    #[test]
+    pub fn test_unknown_settings() {
+        let err = parse("colour = \"#282727\"").unwrap_err();
+        assert!(err.contains("unknown field `colour`"), "{err}");
+        let err = parse("[theme]\nforeground = \"#282727\"").unwrap_err();
+        assert!(err.contains("unknown field `foreground`"), "{err}");
+
+        // the error names the file it came from
+        let dir = std::env::temp_dir().join(format!("tasks_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[pomodoro]\nlunch = \"1h\"\n").unwrap();
+        let err = Config::load(&path).unwrap_err().to_string();
+        assert!(err.starts_with(&path.display().to_string()), "{err}");
+        assert!(err.contains("unknown field `lunch`"), "{err}");
+        _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    pub fn test_invalid_values() {
+        assert_eq!(
+            parse("[theme]\nborder = \"red\"").unwrap_err(),
+            "theme.border must be a color like \"#282727\", found \"red\""
+        );
+        assert!(parse("[theme]\nborder = \"#28272g\"").is_err());
+        assert_eq!(
+            parse("autosave = \"2x\"").unwrap_err(),
+            "autosave must be a duration like \"1h30m\" or \"25m\", found \"2x\""
+        );
+        assert!(parse("[pomodoro]\nwork = \"0m\"").is_err());
+        assert!(parse("sort = \"name\"").is_err());
+        assert!(parse("log_level = \"loud\"").is_err());
+        assert!(parse("[urgency]\nmax_age = 0.0").is_err());
+    }
+
+    #[test]
+    pub fn test_valid_values() {
+        let config = parse(
+            "autosave = \"off\"\ncapacity = \"4h\"\nfile = \"tasks.tl\"\n\
+            [pomodoro]\nwork = \"50m\"\n[theme]\naccent = \"#ABCDEF\"",
+        )
+        .unwrap();
+        assert_eq!(config.autosave, None);
+        assert_eq!(config.capacity, 4 * 3600);
+        assert_eq!(
+            config.file,
+            Some(Path::new("/config/tasks.tl").to_path_buf())
+        );
+        assert_eq!(config.pomodoro_work, Duration::from_secs(50 * 60));
+        assert_eq!(config.theme.accent, "#ABCDEF");
+
+        let config = parse("autosave = \"10s\"").unwrap();
+        assert_eq!(config.autosave, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    pub fn test_keys() {
+        let config = parse("[keys]\nundo = \"ctrl+z\"\nsnooze = \"Z\"\nwake = \"W\"").unwrap();
+        assert_eq!(config.keys.get(KeyBinding::ctrl('z')), Some(Action::Undo));
+        assert_eq!(config.keys.get(KeyBinding::key('u')), None);
+
+        let err = parse("[keys]\nfly = \"f\"").unwrap_err();
+        assert!(err.starts_with("keys: unknown action \"fly\""), "{err}");
+        let err = parse("[keys]\nundo = \"hyper+u\"").unwrap_err();
+        assert!(err.starts_with("keys: unknown key \"hyper+u\""), "{err}");
+    }
+}
@@ -0,0 +1,97 @@
+use anathema::{component::KeyEvent, prelude::Context, state::State};
+
+use crate::{
+    config::Theme,
+    keys::{Action, Keymap},
+};
+
+/// The panes that can have focus. The ids are the `id` attributes in
+/// `main.aml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    List,
+    Editor,
+    Pomodoro,
+    NavBar,
+}
+
+impl Pane {
+    /// the order focus cycles through, the pomodoro only takes focus while
+    /// it runs
+    pub const CYCLE: [Pane; 3] = [Pane::NavBar, Pane::List, Pane::Editor];
+
+    pub fn id(self) -> usize {
+        match self {
+            Pane::List => 0,
+            Pane::Editor => 1,
+            Pane::Pomodoro => 2,
+            Pane::NavBar => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pane::List => "list",
+            Pane::Editor => "editor",
+            Pane::Pomodoro => "pomodoro",
+            Pane::NavBar => "navbar",
+        }
+    }
+
+    pub fn next(self) -> Pane {
+        match Pane::CYCLE.iter().position(|pane| *pane == self) {
+            Some(i) => Pane::CYCLE[(i + 1) % Pane::CYCLE.len()],
+            None => Pane::List,
+        }
+    }
+
+    pub fn prev(self) -> Pane {
+        match Pane::CYCLE.iter().position(|pane| *pane == self) {
+            Some(i) => Pane::CYCLE[(i + Pane::CYCLE.len() - 1) % Pane::CYCLE.len()],
+            None => Pane::List,
+        }
+    }
+
+    pub fn focus<S: State>(self, context: &mut Context<'_, S>) {
+        context.set_focus("id", self.id());
+    }
+}
+
+/// moves focus away from `from` if `key` is bound to `focus_next` or
+/// `focus_prev`, returns whether it did
+pub fn cycle<S: State>(
+    keys: &Keymap,
+    key: &KeyEvent,
+    from: Pane,
+    context: &mut Context<'_, S>,
+) -> bool {
+    match keys.action(key) {
+        Some(Action::FocusNext) => from.next().focus(context),
+        Some(Action::FocusPrev) => from.prev().focus(context),
+        _ => return false,
+    }
+    true
+}
+
+/// the border colors of a pane with and without focus
+#[derive(Debug, Clone)]
+pub struct FocusColors {
+    pub focused: String,
+    pub blurred: String,
+}
+
+impl FocusColors {
+    pub fn new(theme: &Theme) -> Self {
+        Self {
+            focused: theme.accent.clone(),
+            blurred: theme.border.clone(),
+        }
+    }
+
+    pub fn get(&self, focused: bool) -> String {
+        match focused {
+            true => self.focused.clone(),
+            false => self.blurred.clone(),
+        }
+    }
+}
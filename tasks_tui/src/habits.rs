@@ -0,0 +1,96 @@
+use anathema::{
+    component::Component,
+    state::{List, State, Value},
+};
+use tasks_core::{clock, habits::HabitStats, tasks::TaskItem};
+
+/// how many weeks of history the heatmap shows
+const WEEKS: usize = 20;
+
+#[derive(Default, Debug, State)]
+pub struct HabitsState {
+    visible: Value<bool>,
+    name: Value<String>,
+    repeat: Value<String>,
+    current_streak: Value<usize>,
+    longest_streak: Value<usize>,
+    rate: Value<usize>,
+    rows: Value<List<String>>,
+    color: Value<String>,
+}
+
+impl HabitsState {
+    pub fn new(color: String) -> Self {
+        Self {
+            color: Value::new(color),
+            ..Default::default()
+        }
+    }
+
+    fn show(&mut self, item: &TaskItem) {
+        let today = clock::today();
+        let Some(stats) = HabitStats::new(item, today) else {
+            self.visible.set(false);
+            return;
+        };
+
+        self.name.set(item.name().to_string());
+        self.repeat.set(stats.repeat.to_string());
+        self.current_streak.set(stats.current_streak);
+        self.longest_streak.set(stats.longest_streak);
+        self.rate
+            .set((stats.completion_rate * 100.0).round() as usize);
+
+        for i in (0..self.rows.len()).rev() {
+            self.rows.remove(i);
+        }
+        for row in stats.heatmap(today, WEEKS) {
+            let row = row
+                .iter()
+                .map(|count| match count {
+                    None => ' ',
+                    Some(0) => '·',
+                    Some(1) => '▒',
+                    Some(2) => '▓',
+                    Some(_) => '█',
+                })
+                .flat_map(|cell| [cell, ' '])
+                .collect::<String>();
+            self.rows.push_back(row);
+        }
+        self.visible.set(true);
+    }
+}
+
+#[derive(Debug)]
+pub enum HabitsMessage {
+    /// the selected task changed
+    Show(Option<TaskItem>),
+}
+
+/// Streaks and a completion heatmap for the selected task, only visible when
+/// that task repeats
+#[derive(Default)]
+pub struct Habits;
+
+impl Component for Habits {
+    type State = HabitsState;
+    type Message = HabitsMessage;
+
+    fn accept_focus(&self) -> bool {
+        false
+    }
+
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        match message {
+            HabitsMessage::Show(Some(item)) => state.show(&item),
+            HabitsMessage::Show(None) => state.visible.set(false),
+        }
+    }
+}
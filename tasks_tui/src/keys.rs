@@ -0,0 +1,337 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anathema::component::{KeyCode, KeyEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Esc,
+    Tab,
+    BackTab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+/// a key plus whether ctrl was held, written as `ctrl+r` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub key: Key,
+    pub ctrl: bool,
+}
+
+impl KeyBinding {
+    pub const fn key(ch: char) -> Self {
+        Self {
+            key: Key::Char(ch),
+            ctrl: false,
+        }
+    }
+
+    pub const fn named(key: Key) -> Self {
+        Self { key, ctrl: false }
+    }
+
+    pub const fn ctrl(ch: char) -> Self {
+        Self {
+            key: Key::Char(ch),
+            ctrl: true,
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let (ctrl, name) = match source.strip_prefix("ctrl+") {
+            Some(name) => (true, name),
+            None => (false, source),
+        };
+        let key = match name {
+            "enter" => Key::Enter,
+            "esc" => Key::Esc,
+            "tab" => Key::Tab,
+            "backtab" | "shift+tab" => Key::BackTab,
+            "backspace" => Key::Backspace,
+            "delete" => Key::Delete,
+            "space" => Key::Char(' '),
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            "home" => Key::Home,
+            "end" => Key::End,
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Key::Char(ch),
+                    _ => {
+                        return Err(format!(
+                            "unknown key {source:?}, expected a single character, a key name like \
+                            `enter` or `pagedown`, optionally prefixed with `ctrl+`"
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Self { key, ctrl })
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Option<Self> {
+        let key = match event.code {
+            KeyCode::Char(ch) => Key::Char(ch),
+            KeyCode::Enter => Key::Enter,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Tab => Key::Tab,
+            KeyCode::BackTab => Key::BackTab,
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            _ => return None,
+        };
+        Some(Self {
+            key,
+            ctrl: event.ctrl,
+        })
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl+")?;
+        }
+        match self.key {
+            Key::Char(' ') => f.write_str("space"),
+            Key::Char(ch) => write!(f, "{ch}"),
+            key => write!(f, "{}", format!("{key:?}").to_lowercase()),
+        }
+    }
+}
+
+/// Everything the task list does on a key press. The names are the keys of
+/// the `[keys]` table in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Undo,
+    Redo,
+    Timer,
+    Pomodoro,
+    Plan,
+    PlanLater,
+    PlanEarlier,
+    EstimateUp,
+    EstimateDown,
+    Schedule,
+    Remove,
+    Create,
+    Sort,
+    Waiting,
+    Snooze,
+    Wake,
+    /// cycles between not repeating, daily and weekly
+    Repeat,
+    Save,
+    Quit,
+    Down,
+    Up,
+    PageDown,
+    PageUp,
+    /// only moves when pressed twice in a row, like `gg` in vim
+    Top,
+    Bottom,
+    /// moves focus to the editor
+    Open,
+    FocusNext,
+    FocusPrev,
+}
+
+impl Action {
+    /// every action with its name and default key, an action listed more
+    /// than once has several default keys
+    pub const ALL: [(Action, &'static str, KeyBinding); 33] = [
+        (Action::Undo, "undo", KeyBinding::key('u')),
+        (Action::Redo, "redo", KeyBinding::ctrl('r')),
+        (Action::Timer, "timer", KeyBinding::key('s')),
+        (Action::Pomodoro, "pomodoro", KeyBinding::key('p')),
+        (Action::Plan, "plan", KeyBinding::key('P')),
+        (Action::PlanLater, "plan_later", KeyBinding::key('>')),
+        (Action::PlanEarlier, "plan_earlier", KeyBinding::key('<')),
+        (Action::EstimateUp, "estimate_up", KeyBinding::key('+')),
+        (Action::EstimateDown, "estimate_down", KeyBinding::key('-')),
+        (Action::Schedule, "schedule", KeyBinding::key('a')),
+        (Action::Remove, "remove", KeyBinding::key('x')),
+        (Action::Create, "create", KeyBinding::key('c')),
+        (Action::Sort, "sort", KeyBinding::key('o')),
+        (Action::Waiting, "waiting", KeyBinding::key('w')),
+        (Action::Snooze, "snooze", KeyBinding::key('z')),
+        (Action::Wake, "wake", KeyBinding::key('Z')),
+        (Action::Repeat, "repeat", KeyBinding::key('r')),
+        (Action::Save, "save", KeyBinding::ctrl('s')),
+        (Action::Quit, "quit", KeyBinding::key('q')),
+        (Action::Down, "down", KeyBinding::key('j')),
+        (Action::Down, "down", KeyBinding::named(Key::Down)),
+        (Action::Up, "up", KeyBinding::key('k')),
+        (Action::Up, "up", KeyBinding::named(Key::Up)),
+        (Action::PageDown, "page_down", KeyBinding::ctrl('d')),
+        (
+            Action::PageDown,
+            "page_down",
+            KeyBinding::named(Key::PageDown),
+        ),
+        (Action::PageUp, "page_up", KeyBinding::ctrl('u')),
+        (Action::PageUp, "page_up", KeyBinding::named(Key::PageUp)),
+        (Action::Top, "top", KeyBinding::key('g')),
+        (Action::Bottom, "bottom", KeyBinding::key('G')),
+        (Action::Bottom, "bottom", KeyBinding::named(Key::End)),
+        (Action::Open, "open", KeyBinding::named(Key::Enter)),
+        (Action::FocusNext, "focus_next", KeyBinding::named(Key::Tab)),
+        (
+            Action::FocusPrev,
+            "focus_prev",
+            KeyBinding::named(Key::BackTab),
+        ),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Action::ALL
+            .iter()
+            .find(|(action, _, _)| action == self)
+            .map_or("", |(_, name, _)| name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|(action, _, _)| *action)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .iter()
+                .map(|(action, _, key)| (*key, *action))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// the default bindings with `overrides` applied, an action that is
+    /// rebound loses all of its default keys
+    pub fn with_overrides(overrides: &HashMap<String, String>) -> Result<Self, String> {
+        let mut keymap = Keymap::default();
+        let mut rebound = vec![];
+        for (name, key) in overrides {
+            let action = Action::from_name(name).ok_or_else(|| {
+                let mut names: Vec<&str> = Action::ALL.iter().map(|(_, name, _)| *name).collect();
+                names.dedup();
+                format!(
+                    "unknown action {name:?}, expected one of {}",
+                    names.join(", ")
+                )
+            })?;
+            keymap.bindings.retain(|_, bound| *bound != action);
+            rebound.push((KeyBinding::parse(key)?, action));
+        }
+
+        for (key, action) in rebound {
+            if let Some(other) = keymap.bindings.insert(key, action) {
+                return Err(format!(
+                    "{key} is bound to both {} and {}",
+                    other.name(),
+                    action.name()
+                ));
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        KeyBinding::from_event(event).and_then(|key| self.get(key))
+    }
+
+    /// the action bound to `key`
+    pub fn get(&self, key: KeyBinding) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{Action, Key, KeyBinding, Keymap};
+
+    fn overrides(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, key)| (name.to_string(), key.to_string()))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_parse_key() {
+        assert_eq!(KeyBinding::parse("u"), Ok(KeyBinding::key('u')));
+        assert_eq!(KeyBinding::parse("ctrl+r"), Ok(KeyBinding::ctrl('r')));
+        assert_eq!(
+            KeyBinding::parse("pagedown"),
+            Ok(KeyBinding::named(Key::PageDown))
+        );
+        assert_eq!(KeyBinding::parse("space"), Ok(KeyBinding::key(' ')));
+        assert!(KeyBinding::parse("ctrl+").is_err());
+        assert!(KeyBinding::parse("hyper").is_err());
+    }
+
+    #[test]
+    pub fn test_overrides() {
+        let keymap = Keymap::with_overrides(&overrides(&[("undo", "U")])).unwrap();
+        assert_eq!(keymap.get(KeyBinding::key('U')), Some(Action::Undo));
+        // the default key is gone, the others are untouched
+        assert_eq!(keymap.get(KeyBinding::key('u')), None);
+        assert_eq!(keymap.get(KeyBinding::ctrl('r')), Some(Action::Redo));
+
+        // every default key of an action is replaced
+        let keymap = Keymap::with_overrides(&overrides(&[("down", "n")])).unwrap();
+        assert_eq!(keymap.get(KeyBinding::key('j')), None);
+        assert_eq!(keymap.get(KeyBinding::named(Key::Down)), None);
+        assert_eq!(keymap.get(KeyBinding::key('n')), Some(Action::Down));
+    }
+
+    #[test]
+    pub fn test_invalid_overrides() {
+        let err = Keymap::with_overrides(&overrides(&[("frobnicate", "f")])).unwrap_err();
+        assert!(err.starts_with("unknown action \"frobnicate\""), "{err}");
+        assert!(err.contains("undo, redo"), "{err}");
+
+        let err = Keymap::with_overrides(&overrides(&[("undo", "ctrl+uu")])).unwrap_err();
+        assert!(err.starts_with("unknown key \"ctrl+uu\""), "{err}");
+
+        let err = Keymap::with_overrides(&overrides(&[("undo", "j")])).unwrap_err();
+        assert_eq!(err, "j is bound to both down and undo");
+        // swapping two keys is fine
+        assert!(Keymap::with_overrides(&overrides(&[("undo", "j"), ("down", "u")])).is_ok());
+    }
+}
@@ -4,42 +4,89 @@ use anathema::component::Component;
 use anathema::component::ComponentId;
 use anathema::runtime::Runtime;
 use anathema::state::State;
+use anathema::state::Value;
 use anathema::templates::Document;
 
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
 
+use clap::Parser;
+
+use task_editor::EditorMessage;
 use task_editor::TaskEditor;
 use task_editor::TaskEditorState;
 use tracing_subscriber::FmtSubscriber;
 
-use tasks_core::tasks::*;
+use tasks_core::{
+    clock, paths,
+    store::{FileStore, TaskStore},
+};
 
+mod config;
+mod focus;
+mod habits;
+mod keys;
 mod navbar;
+mod planning;
+mod pomodoro;
 mod selection;
 mod task_editor;
+mod templates;
 
+use config::Config;
+use focus::Pane;
+use habits::*;
 use navbar::*;
+use planning::*;
+use pomodoro::*;
 use selection::*;
+use templates::Templates;
+
+#[derive(Debug, Parser)]
+#[command(name = "tasks_tui", about = "Organize a .tl task list in the terminal")]
+struct Args {
+    /// the task file, defaults to $XDG_DATA_HOME/tasks/tasks.tl and is
+    /// created if it doesn't exist
+    file: Option<PathBuf>,
+    /// defaults to $XDG_CONFIG_HOME/tasks/config.toml
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
 
 #[derive(Default)]
 struct App;
 
 #[derive(Debug, State)]
 struct AppState {
+    background: Value<String>,
+    warning: Value<String>,
+    /// overrides that failed to compile, shown on top of everything else
+    template_errors: Value<String>,
     #[state_ignore]
-    id: ComponentId<String>,
+    navbar: ComponentId<bool>,
 }
 
 impl Component for App {
     type State = AppState;
-    type Message = ();
+    /// the current template errors, empty once they are fixed
+    type Message = String;
 
     fn accept_focus(&self) -> bool {
         false
     }
 
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        state.template_errors.set(message);
+    }
+
     fn receive(
         &mut self,
         ident: &str,
@@ -48,69 +95,205 @@ impl Component for App {
         _elements: anathema::widgets::Elements<'_, '_>,
         mut context: anathema::prelude::Context<'_, Self::State>,
     ) {
-        if !ident.eq("task") {
-            tracing::info!("incorrect ident: {ident}, expected ident: task");
-            return;
+        match ident {
+            "task" => {
+                tracing::info!("selected {}", value.to_string());
+                Pane::Editor.focus(&mut context);
+            }
+            "pomodoro_running" => context.emit(state.navbar, value.to_bool()),
+            _ => {
+                tracing::info!("incorrect ident: {ident}, expected ident: task or pomodoro_running")
+            }
         }
-        context.set_focus("id", 1);
-        context.emit(state.id, value.to_string());
     }
 }
 
 fn main() {
+    let args = Args::parse();
+    let config_path = args
+        .config
+        .or_else(|| paths::config_dir().map(|dir| dir.join("config.toml")));
+    let config = match config_path {
+        Some(path) => Config::load(&path).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+
+    let templates = Templates::load(
+        paths::config_dir().map(|dir| dir.join("templates")),
+        paths::state_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("templates"),
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    let template_changes = templates.watch().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+
+    let file = args
+        .file
+        .or_else(|| config.file.clone())
+        .or_else(paths::default_task_file);
+    let Some(path) = file else {
+        eprintln!("no task file given and $HOME is not set");
+        std::process::exit(1);
+    };
+
     setup_hook();
-    setup_logger("log");
+    setup_logger(
+        paths::state_dir().unwrap_or_else(|| PathBuf::from(".")),
+        "tasks.log",
+        config.log_level,
+    );
 
-    let data = std::fs::read_to_string("examples/tasks.tl").unwrap();
-    let task_list = TaskList::deserialize(data).expect("failed with");
+    let mut store = FileStore::new(&path);
+    if let Err(err) = store.create_if_missing() {
+        eprintln!("failed to create {}: {err}", path.display());
+        std::process::exit(1);
+    }
+    if let Err(err) = store.lock() {
+        eprintln!("{err}, is another instance running?");
+        std::process::exit(1);
+    }
+    let mut task_list = store.load().unwrap_or_else(|err| {
+        eprintln!("failed to load {}: {err}", path.display());
+        std::process::exit(1);
+    });
+    task_list.roll_over(clock::today());
+    let planning_events = task_list.subscribe();
+    let changes = store.watch().expect("failed to watch the task file");
 
     let document = Document::new("@main");
 
-    let backend = TuiBackend::builder()
+    let mut backend = TuiBackend::builder()
         .enable_alt_screen()
         .enable_raw_mode()
-        .enable_mouse()
-        .hide_cursor()
-        .finish()
-        .unwrap();
+        .hide_cursor();
+    if config.mouse {
+        backend = backend.enable_mouse();
+    }
+    let backend = backend.finish().unwrap();
 
     let mut runtime = Runtime::builder(document, backend);
 
     let editor = runtime
         .register_component(
             "editor",
-            "./templates/task_editor.aml",
-            TaskEditor {},
-            TaskEditorState::new("".to_string()),
+            templates.path("task_editor.aml"),
+            TaskEditor::new(&config),
+            TaskEditorState::new(&config),
         )
         .unwrap();
 
+    let habits = runtime
+        .register_component(
+            "habits",
+            templates.path("habits.aml"),
+            Habits,
+            HabitsState::new(config.theme.heatmap.clone()),
+        )
+        .expect("failed to register habits component");
+
+    let pomodoro = runtime
+        .register_component(
+            "pomodoro",
+            templates.path("pomodoro.aml"),
+            PomodoroView::new(&config),
+            PomodoroState::new(config.pomodoro_work, config.pomodoro_break),
+        )
+        .expect("failed to register pomodoro component");
+
+    let planning = runtime
+        .register_component(
+            "planning",
+            templates.path("planning.aml"),
+            Planning,
+            PlanningState::new(
+                config.capacity,
+                config.theme.warning.clone(),
+                task_list.clone(),
+            ),
+        )
+        .expect("failed to register planning component");
+
+    let emitter = runtime.emitter();
+    std::thread::spawn(move || {
+        for event in planning_events {
+            if emitter
+                .emit(planning, PlanningMessage::Changed(event))
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
     let selection = runtime
         .register_component(
             "selection",
-            "./templates/list.aml",
-            TaskSelection {},
-            TaskSelectionState::new(task_list),
+            templates.path("list.aml"),
+            TaskSelection::new(store, editor, habits, pomodoro, planning, &config),
+            TaskSelectionState::new(task_list, &config),
         )
         .expect("failed to register list component");
 
-    let _ = runtime.register_component(
-        "main",
-        "./templates/main.aml",
-        App {},
-        AppState { id: editor },
-    );
+    let emitter = runtime.emitter();
+    emitter
+        .emit(editor, EditorMessage::Connect(selection))
+        .expect("failed to connect the editor to the list");
+    emitter
+        .emit(pomodoro, PomodoroMessage::Connect(selection))
+        .expect("failed to connect the pomodoro to the list");
+    std::thread::spawn(move || {
+        for _ in changes {
+            if emitter.emit(selection, SelectionMessage::Reload).is_err() {
+                break;
+            }
+        }
+    });
 
-    let _ = runtime
+    let navbar = runtime
         .register_component(
             "navbar",
-            "./templates/navbar.aml",
-            NavBar {},
-            NavBarState::new(editor, selection, Placement::Relative, 0, 0),
+            templates.path("navbar.aml"),
+            NavBar::new(&config),
+            NavBarState::new(&config),
         )
         .expect("failed to register navbar");
 
-    runtime.finish().unwrap().run();
+    let app = runtime
+        .register_component(
+            "main",
+            templates.path("main.aml"),
+            App {},
+            AppState {
+                background: Value::new(config.theme.background.clone()),
+                warning: Value::new(config.theme.warning.clone()),
+                template_errors: Value::new(templates.errors()),
+                navbar,
+            },
+        )
+        .expect("failed to register main component");
+
+    let emitter = runtime.emitter();
+    std::thread::spawn(move || {
+        for errors in template_changes {
+            if emitter.emit(app, errors).is_err() {
+                break;
+            }
+        }
+    });
+
+    match runtime.finish() {
+        Ok(mut runtime) => runtime.run(),
+        Err(err) => fail(templates.compile_error(err)),
+    }
 }
 
 fn setup_hook() {
@@ -123,17 +306,32 @@ fn setup_hook() {
     }));
 }
 
-fn setup_logger<P: AsRef<Path>>(file: P) {
+/// restores the terminal before exiting with `message`
+fn fail(message: String) -> ! {
+    _ = Screen::new((0, 0)).restore(std::io::stdout());
+    eprintln!("{message}");
+    std::process::exit(1);
+}
+
+/// restores the terminal and exits, components use this since they can't
+/// stop the runtime themselves
+pub fn quit() -> ! {
+    _ = Screen::new((0, 0)).restore(std::io::stdout());
+    std::process::exit(0);
+}
+
+fn setup_logger<P: AsRef<Path>>(dir: P, file: &str, level: tracing::Level) {
+    _ = std::fs::create_dir_all(dir.as_ref());
     _ = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(file.as_ref())
+        .open(dir.as_ref().join(file))
         .expect("truncating log file failed");
 
-    let appender = tracing_appender::rolling::never(".", file);
+    let appender = tracing_appender::rolling::never(dir, file);
     let subscriber = FmtSubscriber::builder()
-        .with_max_level(tracing::Level::TRACE)
+        .with_max_level(level)
         .with_writer(appender)
         .with_ansi(false)
         .finish();
@@ -1,148 +1,160 @@
-use std::{fmt::Display, ops::Range};
-
 use anathema::{
-    component::{Component, ComponentId},
-    state::{CommonVal, List, State, Value},
+    component::{Component, KeyCode},
+    prelude::Context,
+    state::{List, State, Value},
+};
+
+use crate::{
+    config::Config,
+    focus::{self, FocusColors, Pane},
+    keys::Keymap,
 };
 
-#[derive(Default)]
-pub struct NavBar;
+/// the panes the bar can jump to, in the order they are shown
+const ENTRIES: [Pane; 3] = [Pane::List, Pane::Editor, Pane::Pomodoro];
 
-#[allow(dead_code)]
-pub enum Placement {
-    Absolute,
-    Relative,
+/// A row of panes at the top. With focus on the bar left and right pick an
+/// entry and Enter moves focus to it, clicking an entry does the same. The
+/// pomodoro is only listed while one is running.
+pub struct NavBar {
+    keys: Keymap,
+    colors: FocusColors,
 }
 
-impl State for Placement {
-    fn to_common(&self) -> Option<CommonVal<'_>> {
-        match self {
-            Self::Absolute => Some(CommonVal::Str("absolute")),
-            Self::Relative => Some(CommonVal::Str("relative")),
+impl NavBar {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            keys: config.keys.clone(),
+            colors: FocusColors::new(&config.theme),
         }
     }
 }
 
 #[derive(State)]
 pub struct NavBarState {
-    list: Value<List<NavBarId>>,
-    placement: Value<Placement>,
-    selected: Value<Option<NavBarMessage>>,
-    x: Value<usize>,
-    y: Value<usize>,
-}
-
-#[derive(Debug)]
-pub enum NavBarMessage {
-    Editor,
-    Selection,
+    list: Value<List<String>>,
+    /// the entry Enter activates
+    selected: Value<usize>,
+    border_color: Value<String>,
+    #[state_ignore]
+    pomodoro: bool,
 }
 
-impl State for NavBarMessage {
-    fn to_common(&self) -> Option<CommonVal<'_>> {
-        match self {
-            Self::Editor => Some(CommonVal::Str("Editor")),
-            Self::Selection => Some(CommonVal::Str("Selection")),
-        }
+impl NavBarState {
+    pub fn new(config: &Config) -> Self {
+        let mut state = Self {
+            list: List::empty(),
+            selected: Value::new(0),
+            border_color: Value::new(config.theme.border.clone()),
+            pomodoro: false,
+        };
+        state.set_pomodoro(false);
+        state
     }
-}
 
-#[derive(Debug)]
-#[allow(dead_code)]
-pub enum NavBarId {
-    Editor(ComponentId<String>),
-    Selection(ComponentId<String>),
-}
+    /// the panes that can be focused right now
+    fn entries(&self) -> Vec<Pane> {
+        ENTRIES
+            .into_iter()
+            .filter(|pane| *pane != Pane::Pomodoro || self.pomodoro)
+            .collect()
+    }
 
-impl Display for NavBarId {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Editor(_) => f.write_str("Editor"),
-            Self::Selection(_) => f.write_str("Selection"),
+    fn set_pomodoro(&mut self, running: bool) {
+        self.pomodoro = running;
+        for i in (0..self.list.len()).rev() {
+            self.list.remove(i);
+        }
+        for pane in self.entries() {
+            self.list.push_back(pane.name().to_string());
         }
+        let selected = *self.selected.to_ref();
+        self.select(selected);
     }
-}
 
-impl NavBarState {
-    pub fn new(
-        editor: ComponentId<String>,
-        selection: ComponentId<String>,
-        placement: Placement,
-        x: usize,
-        y: usize,
-    ) -> Self {
-        let mut list = List::empty();
-        list.push(NavBarId::Editor(editor));
-        list.push(NavBarId::Selection(selection));
-        Self {
-            list,
-            selected: None.into(),
-            placement: placement.into(),
-            x: x.into(),
-            y: y.into(),
-        }
+    fn select(&mut self, entry: usize) {
+        self.selected
+            .set(entry.min(self.entries().len().saturating_sub(1)));
     }
 }
 
 impl Component for NavBar {
     type State = NavBarState;
-    type Message = NavBarMessage;
+    /// whether a pomodoro is running
+    type Message = bool;
 
     fn message(
         &mut self,
-        message: Self::Message,
+        running: Self::Message,
         state: &mut Self::State,
         _elements: anathema::widgets::Elements<'_, '_>,
-        _context: anathema::prelude::Context<'_, Self::State>,
+        _context: Context<'_, Self::State>,
     ) {
-        state.selected.set(Some(message));
+        state.set_pomodoro(running);
     }
 
-    fn on_mouse(
+    fn on_key(
         &mut self,
-        mouse: anathema::component::MouseEvent,
+        key: anathema::component::KeyEvent,
         state: &mut Self::State,
-        mut elements: anathema::widgets::Elements<'_, '_>,
-        _context: anathema::prelude::Context<'_, Self::State>,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: Context<'_, Self::State>,
     ) {
-        let pos = mouse.pos();
-        let x: usize = pos.x as usize;
-        let buffer_len = 1;
-        if mouse.lsb_down() {
-            elements.by_tag("border").each(|el, _| {
-                let el_size = el.size();
-                let mut start = 1;
-                for item in state.list.to_ref().iter() {
-                    let end = item.to_ref().as_ref().to_string().len().saturating_sub(1);
-                    let range = Range::<usize> {
-                        start,
-                        end: start + end + buffer_len,
-                    };
-                    if range.contains(&x)
-                        //&& pos.x as usize <= el_size.width
-                        && pos.y as usize <= el_size.height
-                    {
-                        match item.to_ref().as_ref() {
-                            NavBarId::Editor(_) => state.selected.set(Some(NavBarMessage::Editor)),
-                            NavBarId::Selection(_) => {
-                                state.selected.set(Some(NavBarMessage::Selection))
-                            }
-                        }
-                        tracing::info!("selected: {:?}", state.selected.to_ref().as_ref());
-                        break;
-                    }
-                    start += end;
+        if focus::cycle(&self.keys, &key, Pane::NavBar, &mut context) {
+            return;
+        }
+        let selected = *state.selected.to_ref();
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => state.select(selected.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => state.select(selected + 1),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(pane) = state.entries().get(selected) {
+                    pane.focus(&mut context);
                 }
-            });
+            }
+            _ => {}
         }
     }
-}
 
-impl State for NavBarId {
-    fn to_common(&self) -> Option<CommonVal<'_>> {
-        match self {
-            NavBarId::Editor(_) => Some(CommonVal::Str("Editor")),
-            NavBarId::Selection(_) => Some(CommonVal::Str("Selection")),
+    fn on_mouse(
+        &mut self,
+        mouse: anathema::component::MouseEvent,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: Context<'_, Self::State>,
+    ) {
+        if !mouse.lsb_down() {
+            return;
+        }
+        let x = mouse.pos().x as usize;
+        // skip the left border, every entry is padded with a space each side
+        let mut start = 1;
+        for (i, pane) in state.entries().iter().enumerate() {
+            let end = start + pane.name().len() + 2;
+            if (start..end).contains(&x) {
+                state.select(i);
+                pane.focus(&mut context);
+                break;
+            }
+            start = end;
         }
     }
+
+    fn on_focus(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: Context<'_, Self::State>,
+    ) {
+        state.border_color.set(self.colors.get(true));
+    }
+
+    fn on_blur(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: Context<'_, Self::State>,
+    ) {
+        state.border_color.set(self.colors.get(false));
+    }
 }
@@ -0,0 +1,139 @@
+use anathema::{
+    component::Component,
+    state::{List, State, Value},
+};
+use tasks_core::{
+    clock,
+    planning::{accuracy, Plan},
+    tasks::{TaskEvent, TaskList},
+};
+
+/// how many days the plan covers, starting today
+pub const PLAN_DAYS: usize = 7;
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Default, Debug, State)]
+pub struct PlanningState {
+    visible: Value<bool>,
+    capacity: Value<String>,
+    days: Value<List<String>>,
+    warnings: Value<List<String>>,
+    unplanned: Value<usize>,
+    estimates: Value<List<String>>,
+    warning_color: Value<String>,
+    #[state_ignore]
+    capacity_secs: u64,
+    /// kept in step with the selection's list through its events
+    #[state_ignore]
+    list: TaskList,
+}
+
+fn clear(list: &mut Value<List<String>>) {
+    for i in (0..list.len()).rev() {
+        list.remove(i);
+    }
+}
+
+impl PlanningState {
+    pub fn new(capacity: u64, warning_color: String, list: TaskList) -> Self {
+        Self {
+            capacity: Value::new(clock::format_duration(capacity)),
+            warning_color: Value::new(warning_color),
+            capacity_secs: capacity,
+            list,
+            ..Default::default()
+        }
+    }
+
+    fn update(&mut self) {
+        let list = &self.list;
+        let today = clock::today();
+        let plan = Plan::new(list, today, PLAN_DAYS, self.capacity_secs);
+
+        clear(&mut self.days);
+        clear(&mut self.warnings);
+        for day in &plan.days {
+            let weekday = WEEKDAYS[(day.day + 3).rem_euclid(7) as usize];
+            let names = day
+                .tasks
+                .iter()
+                .filter_map(|index| list.get(*index))
+                .map(|item| item.name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.days.push_back(format!(
+                "{weekday} {} {} {names}",
+                clock::format_date(day.day),
+                clock::format_duration(day.estimated),
+            ));
+            if day.is_overbooked() {
+                self.warnings.push_back(format!(
+                    "{} is overbooked by {}",
+                    clock::format_date(day.day),
+                    clock::format_duration(day.estimated - day.capacity),
+                ));
+            }
+        }
+        self.unplanned.set(plan.unplanned.len());
+
+        clear(&mut self.estimates);
+        for accuracy in accuracy(list, clock::now()) {
+            let Some(item) = list.get(accuracy.index) else {
+                continue;
+            };
+            self.estimates.push_back(format!(
+                "{} {} / {} ({:.0}%)",
+                item.name(),
+                clock::format_duration(accuracy.tracked),
+                clock::format_duration(accuracy.estimate),
+                accuracy.ratio() * 100.0,
+            ));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PlanningMessage {
+    Toggle,
+    /// the task list changed
+    Changed(TaskEvent),
+}
+
+/// The week ahead: what is planned for each day against the daily capacity,
+/// and how estimates compare to the time actually tracked
+#[derive(Default)]
+pub struct Planning;
+
+impl Component for Planning {
+    type State = PlanningState;
+    type Message = PlanningMessage;
+
+    fn accept_focus(&self) -> bool {
+        false
+    }
+
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        match message {
+            PlanningMessage::Toggle => {
+                let visible = !*state.visible.to_ref();
+                state.visible.set(visible);
+                if visible {
+                    state.update();
+                }
+            }
+            PlanningMessage::Changed(event) => {
+                state.list.apply(event);
+                if *state.visible.to_ref() {
+                    state.update();
+                }
+            }
+        }
+    }
+}
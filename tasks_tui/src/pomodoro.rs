@@ -0,0 +1,185 @@
+use std::{io::Write, time::Duration};
+
+use anathema::{
+    component::{Component, ComponentId, KeyCode},
+    prelude::Context,
+    state::{State, Value},
+};
+use tasks_core::{
+    clock,
+    pomodoro::{Phase, Pomodoro},
+    tasks::TaskItem,
+};
+
+use crate::{
+    config::Config,
+    focus::{self, Pane},
+    keys::Keymap,
+    selection::SelectionMessage,
+};
+
+#[derive(Debug, State)]
+pub struct PomodoroState {
+    active: Value<bool>,
+    paused: Value<bool>,
+    name: Value<String>,
+    phase: Value<String>,
+    remaining: Value<String>,
+    done: Value<usize>,
+    /// the task as it was when the session started
+    #[state_ignore]
+    task: Option<TaskItem>,
+    #[state_ignore]
+    pomodoro: Option<Pomodoro>,
+    #[state_ignore]
+    work: Duration,
+    #[state_ignore]
+    rest: Duration,
+}
+
+impl PomodoroState {
+    pub fn new(work: Duration, rest: Duration) -> Self {
+        Self {
+            active: Value::new(false),
+            paused: Value::new(false),
+            name: Value::default(),
+            phase: Value::default(),
+            remaining: Value::default(),
+            done: Value::new(0),
+            task: None,
+            pomodoro: None,
+            work,
+            rest,
+        }
+    }
+
+    fn start(&mut self, item: &TaskItem) {
+        self.pomodoro = Some(Pomodoro::new(self.work, self.rest));
+        self.name.set(item.name().to_string());
+        self.done.set(item.pomodoros());
+        self.task = Some(item.clone());
+        self.paused.set(false);
+        self.active.set(true);
+        self.update();
+    }
+
+    fn stop(&mut self) {
+        self.pomodoro = None;
+        self.task = None;
+        self.active.set(false);
+    }
+
+    fn update(&mut self) {
+        let Some(pomodoro) = &self.pomodoro else {
+            return;
+        };
+        self.phase.set(pomodoro.phase().to_string());
+        self.remaining
+            .set(clock::format_duration(pomodoro.remaining().as_secs()));
+        self.paused.set(pomodoro.is_paused());
+    }
+}
+
+#[derive(Debug)]
+pub enum PomodoroMessage {
+    /// where finished work phases are sent, the list is registered after the
+    /// pomodoro so it can't be passed in when the pomodoro is created
+    Connect(ComponentId<SelectionMessage>),
+    /// start a focus session on the task
+    Start(TaskItem),
+}
+
+fn bell() {
+    let mut stdout = std::io::stdout();
+    _ = stdout.write_all(b"\x07");
+    _ = stdout.flush();
+}
+
+/// A work/break countdown bound to a single task. Every finished work phase is
+/// sent to the list so the count can be stored on the task, and `running` is
+/// published whenever a session starts or stops.
+pub struct PomodoroView {
+    selection: Option<ComponentId<SelectionMessage>>,
+    keys: Keymap,
+}
+
+impl PomodoroView {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            selection: None,
+            keys: config.keys.clone(),
+        }
+    }
+}
+
+impl Component for PomodoroView {
+    type State = PomodoroState;
+    type Message = PomodoroMessage;
+
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: Context<'_, Self::State>,
+    ) {
+        match message {
+            PomodoroMessage::Connect(selection) => self.selection = Some(selection),
+            PomodoroMessage::Start(item) => {
+                state.start(&item);
+                context.publish("running", |state| &state.active);
+                Pane::Pomodoro.focus(&mut context);
+            }
+        }
+    }
+
+    fn on_key(
+        &mut self,
+        key: anathema::component::KeyEvent,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: Context<'_, Self::State>,
+    ) {
+        if focus::cycle(&self.keys, &key, Pane::Pomodoro, &mut context) {
+            return;
+        }
+        match key.code {
+            KeyCode::Char(' ') => {
+                if let Some(pomodoro) = &mut state.pomodoro {
+                    pomodoro.toggle_pause();
+                }
+                state.update();
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                state.stop();
+                context.publish("running", |state| &state.active);
+                Pane::List.focus(&mut context);
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: Context<'_, Self::State>,
+        dt: Duration,
+    ) {
+        let Some(pomodoro) = &mut state.pomodoro else {
+            return;
+        };
+        match pomodoro.tick(dt) {
+            Some(Phase::Work) => {
+                bell();
+                *state.done.to_mut() += 1;
+                if let (Some(selection), Some(task)) = (self.selection, &state.task) {
+                    context.emit(selection, SelectionMessage::PomodoroDone(task.clone()));
+                }
+            }
+            Some(Phase::Break) => bell(),
+            None => {}
+        }
+        state.update();
+    }
+}
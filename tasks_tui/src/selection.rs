@@ -1,18 +1,141 @@
+use std::time::{Duration, Instant};
+
 use anathema::{
-    component::{Component, KeyCode, MouseEvent, MouseState},
+    component::{Component, ComponentId, KeyCode, MouseEvent, MouseState},
     default_widgets::Overflow,
+    prelude::Context,
     state::{List, State, Value},
+    widgets::Elements,
+};
+use tasks_core::{
+    clock,
+    history::{History, TaskCommand},
+    planning::Plan,
+    store::{FileStore, TaskStore},
+    tasks::{Repeat, TaskError, TaskItem, TaskList},
+    urgency::UrgencyConfig,
 };
-use tasks_core::tasks::{TaskItem, TaskList};
+
+use crate::{
+    config::Config,
+    focus::{self, FocusColors, Pane},
+    habits::HabitsMessage,
+    keys::{Action, Keymap},
+    planning::{PlanningMessage, PLAN_DAYS},
+    pomodoro::PomodoroMessage,
+    task_editor::EditorMessage,
+};
+
+/// how much an estimate grows or shrinks by per key press
+const ESTIMATE_STEP: u64 = 30 * 60;
+/// how many days snoozing defers a task by
+const SNOOZE_DAYS: i64 = 7;
+
+/// the order tasks are listed in
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Sort {
+    #[default]
+    File,
+    Urgency,
+}
+
+/// which tasks are listed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// everything that isn't deferred
+    #[default]
+    Active,
+    /// only deferred tasks, the soonest first
+    Waiting,
+}
+
+/// a question shown under the list that takes over the keyboard until it is
+/// answered
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    /// quitting with unsaved changes
+    Quit,
+    /// saving or reloading while someone else changed the file
+    Conflict,
+}
+
+impl Prompt {
+    fn text(&self) -> &'static str {
+        match self {
+            Prompt::Quit => "unsaved changes: (s)ave and quit, (d)iscard and quit, esc to cancel",
+            Prompt::Conflict => {
+                "the task file changed on disk: (m)erge, (o)verwrite, (r)eload, esc to decide later"
+            }
+        }
+    }
+}
+
+/// whether `a` and `b` are copies of the same task, a task is known by when
+/// it was created and its name
+pub fn same_task(a: &TaskItem, b: &TaskItem) -> bool {
+    a.created_at() == b.created_at() && a.name() == b.name()
+}
+
+fn serialized(list: &TaskList) -> Vec<u8> {
+    let mut source = vec![];
+    _ = list.serialize(&mut source);
+    source
+}
 
 #[derive(Default, Debug, State)]
 pub struct TaskSelectionState {
     selection: Value<List<String>>,
     border_width: Value<usize>,
+    border_color: Value<String>,
     selected: Value<Option<usize>>,
+    /// name of the selected task, published so the app can focus the editor
     selected_item: Value<String>,
+    timer: Value<String>,
+    tracked_today: Value<String>,
+    accent: Value<String>,
+    sorted_by: Value<String>,
+    filter: Value<String>,
+    unsaved: Value<bool>,
+    prompt: Value<String>,
+    /// why the last save or edit was refused
+    status: Value<String>,
     #[state_ignore]
     list: TaskList,
+    /// `list` as it was last loaded or saved
+    #[state_ignore]
+    saved: Vec<u8>,
+    #[state_ignore]
+    pending: Option<Prompt>,
+    /// when the last key was pressed, autosave waits for a pause in typing
+    #[state_ignore]
+    last_input: Option<Instant>,
+    /// input arrived since `unsaved` was last updated
+    #[state_ignore]
+    touched: bool,
+    /// the first row the list is scrolled to
+    #[state_ignore]
+    offset: usize,
+    /// rows that fit in the list, as of the last time it was scrolled
+    #[state_ignore]
+    viewport: usize,
+    /// the top key was pressed once, the next press moves to the top
+    #[state_ignore]
+    pending_top: bool,
+    /// indices into `list` in the order they are displayed, `selected` is a
+    /// position in this
+    #[state_ignore]
+    order: Vec<usize>,
+    #[state_ignore]
+    sort: Sort,
+    #[state_ignore]
+    filtered: Filter,
+    /// the day `order` was built on, deferred tasks come back when it changes
+    #[state_ignore]
+    today: i64,
+    #[state_ignore]
+    urgency: UrgencyConfig,
+    #[state_ignore]
+    history: History,
     #[state_ignore]
     buffer: String,
     #[state_ignore]
@@ -20,100 +143,832 @@ pub struct TaskSelectionState {
 }
 
 impl TaskSelectionState {
-    pub fn new(list: TaskList) -> Self {
-        let mut data = List::empty();
-        let mut border_width = 0;
+    pub fn new(list: TaskList, config: &Config) -> Self {
+        let mut state = Self {
+            selected: Value::new(None),
+            accent: Value::new(config.theme.accent.clone()),
+            border_color: Value::new(config.theme.border.clone()),
+            list,
+            urgency: config.urgency.clone(),
+            ..Default::default()
+        };
+        state.saved = serialized(&state.list);
+        state.set_view(config.sort, config.filter);
+        state
+    }
 
-        for item in list.iter() {
-            let name = item.name().to_string();
+    fn is_unsaved(&self) -> bool {
+        serialized(&self.list) != self.saved
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved = serialized(&self.list);
+        self.unsaved.set(false);
+        self.status.set(String::new());
+    }
+
+    fn ask(&mut self, prompt: Option<Prompt>) {
+        self.pending = prompt;
+        self.prompt
+            .set(prompt.map_or("", |prompt| prompt.text()).to_string());
+    }
+
+    /// rebuilds the displayed names from `list`
+    fn refresh(&mut self) {
+        for i in (0..self.selection.len()).rev() {
+            self.selection.remove(i);
+        }
+
+        self.today = clock::today();
+        self.order = match self.filtered {
+            Filter::Waiting => self.list.waiting(self.today),
+            Filter::Active => {
+                let order = match self.sort {
+                    Sort::File => (0..self.list.len()).collect(),
+                    Sort::Urgency => self.urgency.sort(&self.list, clock::now()),
+                };
+                order
+                    .into_iter()
+                    .filter(|index| !self.list[*index].is_waiting(self.today))
+                    .collect()
+            }
+        };
+
+        let mut border_width = 0;
+        for index in &self.order {
+            let item = &self.list[*index];
+            let name = match (self.filtered, item.wait()) {
+                (Filter::Waiting, Some(wait)) => {
+                    format!("{} ({})", item.name(), clock::format_date(wait))
+                }
+                _ => item.name().to_string(),
+            };
             if name.len() > border_width {
                 border_width = name.len();
             }
-            data.push_back(name);
+            self.selection.push_back(name);
         }
 
         // += 2 because the left and right sides of the border are 1 cell
         border_width += 9;
 
-        Self {
-            selection: data,
-            list,
-            border_width: Value::new(border_width),
-            selected: Value::new(None),
-            ..Default::default()
+        self.border_width.set(border_width);
+        self.update_timer();
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.selected_task().map(|task| task.name().to_string())
+    }
+
+    /// index into `list` of the selected task
+    fn selected_index(&self) -> Option<usize> {
+        self.selected
+            .to_number()
+            .and_then(|n| self.order.get(n.as_uint()).copied())
+    }
+
+    fn selected_task(&self) -> Option<&TaskItem> {
+        self.selected_index().and_then(|index| self.list.get(index))
+    }
+
+    /// moves the selection `rows` rows down, or up for a negative count.
+    /// Without a selection the first row is selected.
+    fn move_selection(&mut self, rows: isize) -> bool {
+        let Some(last) = self.order.len().checked_sub(1) else {
+            return false;
+        };
+        let row = match self.selected.to_number().map(|n| n.as_uint()) {
+            Some(row) => row.saturating_add_signed(rows).min(last),
+            None => 0,
+        };
+        if self.selected.to_number().map(|n| n.as_uint()) == Some(row) {
+            return false;
         }
+        self.select_row(Some(row));
+        true
+    }
+
+    /// how many rows page up and down move by
+    fn page(&self) -> isize {
+        self.viewport.max(1) as isize
     }
+
+    fn select_row(&mut self, row: Option<usize>) {
+        let item = row
+            .and_then(|row| self.order.get(row))
+            .and_then(|index| self.list.get(*index));
+        self.selected_item
+            .set(item.map(|task| task.name().to_string()).unwrap_or_default());
+        self.selected.set(row);
+    }
+
+    fn select_by_name(&mut self, name: Option<String>) {
+        let row = name.and_then(|name| {
+            self.order
+                .iter()
+                .position(|index| self.list[*index].name() == name)
+        });
+        self.select_row(row);
+    }
+
+    /// records `command` and redraws the list, keeping the selection on the
+    /// same task when it moves
+    fn apply(&mut self, command: TaskCommand) -> bool {
+        let selected = self.selected_name();
+        if !self.history.apply(&mut self.list, command) {
+            return false;
+        }
+        self.refresh();
+        self.select_by_name(selected);
+        true
+    }
+
+    /// changes how the list is ordered and which tasks it shows
+    fn set_view(&mut self, sort: Sort, filter: Filter) {
+        let selected = self.selected_name();
+        self.sort = sort;
+        self.filtered = filter;
+        self.sorted_by.set(match sort {
+            Sort::File => String::new(),
+            Sort::Urgency => "urgency".to_string(),
+        });
+        self.filter.set(match filter {
+            Filter::Active => String::new(),
+            Filter::Waiting => "waiting".to_string(),
+        });
+        self.refresh();
+        self.select_by_name(selected);
+    }
+
+    /// switches between file order and most urgent first
+    pub fn toggle_sort(&mut self) {
+        let sort = match self.sort {
+            Sort::File => Sort::Urgency,
+            Sort::Urgency => Sort::File,
+        };
+        self.set_view(sort, self.filtered);
+    }
+
+    /// switches between the default list and the deferred tasks
+    pub fn toggle_waiting(&mut self) {
+        let filter = match self.filtered {
+            Filter::Active => Filter::Waiting,
+            Filter::Waiting => Filter::Active,
+        };
+        self.set_view(self.sort, filter);
+    }
+
+    /// defers the selected task by another `SNOOZE_DAYS`
+    pub fn snooze(&mut self) -> bool {
+        let today = clock::today();
+        self.edit_selected(|item| {
+            let from = item.wait().unwrap_or(today).max(today);
+            item.set_wait(Some(from + SNOOZE_DAYS));
+        })
+    }
+
+    pub fn wake(&mut self) -> bool {
+        self.edit_selected(|item| item.set_wait(None))
+    }
+
+    /// makes the selected task a daily habit, then a weekly one, then a
+    /// plain task again
+    pub fn cycle_repeat(&mut self) -> bool {
+        self.edit_selected(|item| {
+            item.set_repeat(match item.repeat() {
+                None => Some(Repeat::Daily),
+                Some(Repeat::Daily) => Some(Repeat::Weekly),
+                Some(Repeat::Weekly) => None,
+            })
+        })
+    }
+
+    /// removes the selected task and selects the one above it
+    pub fn remove_selected(&mut self) -> bool {
+        let (Some(row), Some(index)) = (
+            self.selected.to_number().map(|n| n.as_uint()),
+            self.selected_index(),
+        ) else {
+            return false;
+        };
+        if !self
+            .history
+            .apply(&mut self.list, TaskCommand::Remove(index))
+        {
+            return false;
+        }
+        self.refresh();
+        let row = row.saturating_sub(1);
+        self.select_row((row < self.order.len()).then_some(row));
+        true
+    }
+
+    /// swaps in a freshly loaded list, keeping the selection on the task with
+    /// the same name if it still exists
+    pub fn reload(&mut self, list: TaskList) {
+        let selected = self.selected_name();
+        self.saved = serialized(&list);
+        self.list.reset(list);
+        self.list.roll_over(clock::today());
+        self.unsaved.set(self.is_unsaved());
+        // the recorded commands refer to indices in the old list
+        self.history.clear();
+        self.refresh();
+        self.select_by_name(selected);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let selected = self.selected_name();
+        if !self.history.undo(&mut self.list) {
+            return false;
+        }
+        self.refresh();
+        self.select_by_name(selected);
+        true
+    }
+
+    /// starts the timer on the selected task, or stops it if it is running.
+    /// Only one timer runs at a time so starting one stops any other.
+    pub fn toggle_timer(&mut self) -> bool {
+        let Some(index) = self.selected_index() else {
+            return false;
+        };
+        let now = clock::now();
+        let mut commands = vec![];
+
+        if let Some(running) = self.list.running().filter(|running| *running != index) {
+            let mut item = self.list[running].clone();
+            item.stop_timer(now);
+            commands.push(TaskCommand::Replace(running, item));
+        }
+
+        let Some(mut item) = self.list.get(index).cloned() else {
+            return false;
+        };
+        if item.stop_timer(now).is_none() {
+            item.start_timer(now);
+        }
+        commands.push(TaskCommand::Replace(index, item));
+        // one key press is one undo step
+        self.apply(TaskCommand::Batch(commands))
+    }
+
+    /// refreshes the running timer and today's total
+    fn update_timer(&mut self) {
+        let now = clock::now();
+        let timer = self
+            .list
+            .running()
+            .and_then(|running| self.list.get(running))
+            .map(|item| {
+                let since = item.running_since().unwrap_or(now);
+                format!(
+                    "{} {}",
+                    item.name(),
+                    clock::format_duration(now.saturating_sub(since))
+                )
+            })
+            .unwrap_or_default();
+        self.timer.set(timer);
+
+        let today = self.list.tracked_on(clock::today(), now);
+        self.tracked_today
+            .set(format!("today {}", clock::format_duration(today)));
+    }
+
+    /// the index of `item` in the list. Tasks are told apart by when they
+    /// were created, and by name when that is unknown or shared.
+    fn find(&self, item: &TaskItem) -> Option<usize> {
+        let same_age: Vec<usize> = (0..self.list.len())
+            .filter(|i| self.list[*i].created_at() == item.created_at())
+            .collect();
+        match same_age.as_slice() {
+            [index] if item.created_at().is_some() => Some(*index),
+            candidates => candidates
+                .iter()
+                .copied()
+                .find(|i| self.list[*i].name() == item.name()),
+        }
+    }
+
+    /// counts a finished pomodoro on `task`, which may have been edited
+    /// since the session started
+    pub fn add_pomodoro(&mut self, task: &TaskItem) -> bool {
+        let Some(index) = self.find(task) else {
+            return false;
+        };
+        let mut item = self.list[index].clone();
+        item.add_pomodoro();
+        self.apply(TaskCommand::Replace(index, item))
+    }
+
+    /// applies `edit` to a copy of the selected task and records it
+    fn edit_selected(&mut self, edit: impl FnOnce(&mut TaskItem)) -> bool {
+        let Some(index) = self.selected_index() else {
+            return false;
+        };
+        let Some(mut item) = self.list.get(index).cloned() else {
+            return false;
+        };
+        edit(&mut item);
+        self.apply(TaskCommand::Replace(index, item))
+    }
+
+    /// sets the fields the editor shows on the task at `index` as one undo
+    /// step, everything else about the task stays as it is in the list. An
+    /// empty name is refused, and so is an edit of a task that is no longer
+    /// at `index`, the `shown` one.
+    pub fn edit_task(
+        &mut self,
+        index: usize,
+        shown: &TaskItem,
+        name: String,
+        status: bool,
+        data: String,
+    ) -> bool {
+        let Some(mut item) = self.list.get(index).cloned() else {
+            self.status.set("the edited task is gone".to_string());
+            return false;
+        };
+        if !same_task(&item, shown) {
+            self.status.set("the edited task is gone".to_string());
+            return false;
+        }
+        if name.trim().is_empty() {
+            self.status.set("a task needs a name".to_string());
+            return false;
+        }
+        if item.name() == name && item.status() == status && item.data() == data {
+            return false;
+        }
+        item.set_name(name.clone());
+        item.set_status(status);
+        item.set_data(data);
+        if !self.apply(TaskCommand::Replace(index, item)) {
+            return false;
+        }
+        // `apply` looks the selection up by its old name
+        if self.selected_index().is_none() {
+            self.select_by_name(Some(name));
+        }
+        true
+    }
+
+    /// moves the selected task `days` days, an unplanned task starts at today
+    pub fn shift_planned(&mut self, days: i64) -> bool {
+        self.edit_selected(|item| {
+            let day = item.planned().unwrap_or(clock::today() - days);
+            item.set_planned(Some((day + days).max(clock::today())));
+        })
+    }
+
+    pub fn grow_estimate(&mut self) -> bool {
+        self.edit_selected(|item| {
+            item.set_estimate(Some(item.estimate().unwrap_or_default() + ESTIMATE_STEP))
+        })
+    }
+
+    /// dropping below one step removes the estimate
+    pub fn shrink_estimate(&mut self) -> bool {
+        self.edit_selected(|item| {
+            let estimate = item.estimate().unwrap_or_default();
+            item.set_estimate(estimate.checked_sub(ESTIMATE_STEP).filter(|e| *e > 0))
+        })
+    }
+
+    /// assigns unplanned tasks to the coming days, undone as a single step
+    pub fn schedule(&mut self, capacity: u64) -> bool {
+        let mut plan = Plan::new(&self.list, clock::today(), PLAN_DAYS, capacity);
+        let commands = plan
+            .schedule(&self.list)
+            .into_iter()
+            .filter_map(|(index, day)| {
+                let mut item = self.list.get(index)?.clone();
+                item.set_planned(Some(day));
+                Some(TaskCommand::Replace(index, item))
+            })
+            .collect::<Vec<_>>();
+        if commands.is_empty() {
+            return false;
+        }
+        self.apply(TaskCommand::Batch(commands))
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let selected = self.selected_name();
+        if !self.history.redo(&mut self.list) {
+            return false;
+        }
+        self.refresh();
+        self.select_by_name(selected);
+        true
+    }
+}
+
+#[derive(Debug)]
+pub enum SelectionMessage {
+    /// the task file changed on disk
+    Reload,
+    /// a focus session on the task finished, the task is as it was when the
+    /// session started
+    PomodoroDone(TaskItem),
+    /// the editor changed the task at `index`, `task` is the task as the
+    /// editor was showing it
+    Edit {
+        index: usize,
+        task: TaskItem,
+        name: String,
+        status: bool,
+        data: String,
+    },
 }
 
-#[derive(Default)]
-pub struct TaskSelection;
+pub struct TaskSelection {
+    store: FileStore,
+    editor: ComponentId<EditorMessage>,
+    habits: ComponentId<HabitsMessage>,
+    pomodoro: ComponentId<PomodoroMessage>,
+    planning: ComponentId<PlanningMessage>,
+    /// seconds of work planned per day
+    capacity: u64,
+    autosave: Option<Duration>,
+    keys: Keymap,
+    colors: FocusColors,
+}
+
+impl TaskSelection {
+    pub fn new(
+        store: FileStore,
+        editor: ComponentId<EditorMessage>,
+        habits: ComponentId<HabitsMessage>,
+        pomodoro: ComponentId<PomodoroMessage>,
+        planning: ComponentId<PlanningMessage>,
+        config: &Config,
+    ) -> Self {
+        Self {
+            store,
+            editor,
+            habits,
+            pomodoro,
+            planning,
+            capacity: config.capacity,
+            autosave: config.autosave,
+            keys: config.keys.clone(),
+            colors: FocusColors::new(&config.theme),
+        }
+    }
+
+    /// writes the list to disk, asking what to do if someone else changed
+    /// the file in the meantime
+    fn save(&mut self, state: &mut TaskSelectionState) -> bool {
+        match self.store.save(&state.list) {
+            Ok(()) => {
+                state.mark_saved();
+                true
+            }
+            Err(TaskError::Conflict(_)) => {
+                state.ask(Some(Prompt::Conflict));
+                false
+            }
+            Err(err) => {
+                tracing::error!("failed to save: {err}");
+                state.status.set(format!("failed to save: {err}"));
+                false
+            }
+        }
+    }
+
+    /// saves without looking at what is on disk, used once a conflict has
+    /// been settled
+    fn overwrite(&mut self, state: &mut TaskSelectionState) {
+        match self.store.overwrite(&state.list) {
+            Ok(()) => state.mark_saved(),
+            Err(err) => {
+                tracing::error!("failed to save: {err}");
+                state.status.set(format!("failed to save: {err}"));
+            }
+        }
+    }
+
+    fn quit(&mut self) -> ! {
+        _ = self.store.unlock();
+        crate::quit()
+    }
+
+    /// handles a key while a prompt is shown, every other key is ignored
+    fn answer(
+        &mut self,
+        prompt: Prompt,
+        key: KeyCode,
+        state: &mut TaskSelectionState,
+        context: &mut Context<'_, TaskSelectionState>,
+    ) {
+        match (prompt, key) {
+            (_, KeyCode::Esc) => state.ask(None),
+            (Prompt::Quit, KeyCode::Char('s')) => {
+                state.ask(None);
+                if self.save(state) {
+                    self.quit();
+                }
+            }
+            (Prompt::Quit, KeyCode::Char('d')) => self.quit(),
+            (Prompt::Conflict, KeyCode::Char('m')) => {
+                state.ask(None);
+                match self.store.merge(&state.list) {
+                    Ok(merged) => {
+                        state.reload(merged);
+                        self.overwrite(state);
+                        self.announce(state, context);
+                    }
+                    Err(err) => {
+                        // the file still counts as changed, overwrite or reload
+                        state.status.set(format!("failed to merge: {err}"));
+                        state.ask(Some(Prompt::Conflict));
+                    }
+                }
+            }
+            (Prompt::Conflict, KeyCode::Char('o')) => {
+                state.ask(None);
+                self.overwrite(state);
+            }
+            (Prompt::Conflict, KeyCode::Char('r')) => {
+                state.ask(None);
+                match self.store.load() {
+                    Ok(list) => {
+                        state.reload(list);
+                        self.announce(state, context);
+                    }
+                    Err(err) => state.status.set(format!("failed to reload: {err}")),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// moves focus to the editor by publishing the selected task
+    fn open(&self, context: &mut Context<'_, TaskSelectionState>) {
+        context.publish("selection_bar", |state| &state.selected_item);
+    }
+
+    /// scrolls the list just enough for the selected row to be on screen
+    fn keep_visible(state: &mut TaskSelectionState, elements: &mut Elements<'_, '_>) {
+        let Some(row) = state.selected.to_number().map(|n| n.as_uint()) else {
+            return;
+        };
+        elements.by_tag("overflow").first(|el, _| {
+            let height = el.size().height.max(1);
+            state.viewport = height;
+            let overflow = el.to::<Overflow>();
+            if row < state.offset {
+                overflow.scroll_up_by(state.offset - row);
+                state.offset = row;
+            } else if row >= state.offset + height {
+                let by = row + 1 - height - state.offset;
+                overflow.scroll_down_by(by);
+                state.offset += by;
+            }
+        });
+    }
+
+    /// tells the other panes which task is selected now
+    fn announce(&self, state: &TaskSelectionState, context: &mut Context<'_, TaskSelectionState>) {
+        let task = state.selected_index().zip(state.selected_task().cloned());
+        context.emit(self.editor, EditorMessage::Show(task));
+        let item = state.selected_task().cloned();
+        context.emit(self.habits, HabitsMessage::Show(item));
+    }
+}
 
 impl Component for TaskSelection {
     type State = TaskSelectionState;
-    type Message = String;
+    type Message = SelectionMessage;
+
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        mut context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        match message {
+            SelectionMessage::Reload => {
+                // our own saves trigger the watcher too
+                if !self.store.changed_on_disk() {
+                    return;
+                }
+                // don't throw away our own changes, let the user decide
+                if state.is_unsaved() {
+                    state.ask(Some(Prompt::Conflict));
+                    return;
+                }
+                match self.store.load() {
+                    Ok(list) => {
+                        tracing::info!("reloaded {}", self.store.path().display());
+                        state.reload(list);
+                        self.announce(state, &mut context);
+                    }
+                    Err(err) => tracing::info!("failed to reload: {err}"),
+                }
+            }
+            SelectionMessage::Edit {
+                index,
+                task,
+                name,
+                status,
+                data,
+            } => {
+                state.last_input = Some(Instant::now());
+                state.touched = true;
+                state.edit_task(index, &task, name, status, data);
+                // a refused edit puts the editor back in sync as well
+                self.announce(state, &mut context);
+            }
+            SelectionMessage::PomodoroDone(task) => {
+                state.last_input = Some(Instant::now());
+                state.touched = true;
+                if state.add_pomodoro(&task) {
+                    self.announce(state, &mut context);
+                }
+            }
+        }
+    }
 
     fn on_key(
         &mut self,
         key: anathema::component::KeyEvent,
         state: &mut Self::State,
-        _elements: anathema::widgets::Elements<'_, '_>,
-        _context: anathema::prelude::Context<'_, Self::State>,
+        mut elements: anathema::widgets::Elements<'_, '_>,
+        mut context: anathema::prelude::Context<'_, Self::State>,
     ) {
         tracing::info!("from selection");
         if state.creating_item {
             tracing::info!("creating item mode: {}", state.buffer);
         }
+        if let Some(prompt) = state.pending {
+            self.answer(prompt, key.code, state, &mut context);
+            return;
+        }
+        state.last_input = Some(Instant::now());
+        state.touched = true;
         match key.code {
-            KeyCode::Char('x') => {
-                if state.list.is_empty() | state.selected.to_ref().is_none() {
-                    state.selected_item.set(String::default());
-                    return;
+            KeyCode::Char(ch) if state.creating_item => {
+                state.buffer.push(ch);
+                return;
+            }
+            KeyCode::Backspace if state.creating_item => {
+                state.buffer.pop();
+                return;
+            }
+            KeyCode::Esc => {
+                state.creating_item = false;
+                state.buffer.clear();
+                return;
+            }
+            KeyCode::Enter if state.creating_item => {
+                match state.buffer.trim().is_empty() {
+                    true => state.status.set("a task needs a name".to_string()),
+                    false => {
+                        let item = TaskItem::new(state.buffer.clone(), String::default(), false);
+                        state.apply(TaskCommand::Push(item));
+                    }
                 }
+                state.buffer.clear();
+                state.creating_item = false;
+                return;
+            }
+            _ => {}
+        }
 
-                let mut index = state
-                    .selected
-                    .to_ref()
-                    .to_number()
-                    .map_or(0, |n| n.as_uint());
-
-                if index.saturating_sub(1) > state.selection.len() {
-                    return;
+        let Some(action) = self.keys.action(&key) else {
+            state.pending_top = false;
+            return;
+        };
+        let pending_top = std::mem::take(&mut state.pending_top);
+        let changed = match action {
+            Action::Down => state.move_selection(1),
+            Action::Up => state.move_selection(-1),
+            Action::PageDown => state.move_selection(state.page()),
+            Action::PageUp => state.move_selection(-state.page()),
+            Action::Top => {
+                state.pending_top = !pending_top;
+                pending_top && state.move_selection(isize::MIN)
+            }
+            Action::Bottom => state.move_selection(isize::MAX),
+            Action::Open => {
+                if state.selected_task().is_some() {
+                    self.open(&mut context);
                 }
-
-                state.selection.remove(index);
-                state.list.remove(index);
-                index = index.saturating_sub(1);
-
-                if let Some(str) = state.selection.to_ref().get(index) {
-                    let item = str.to_ref().to_string();
-                    state.selected_item.set(item);
-
-                    state.selected.set(Some(index));
+                false
+            }
+            Action::FocusNext | Action::FocusPrev => {
+                focus::cycle(&self.keys, &key, Pane::List, &mut context);
+                false
+            }
+            Action::Undo => state.undo(),
+            Action::Redo => state.redo(),
+            Action::Timer => state.toggle_timer(),
+            Action::Pomodoro => {
+                if let Some(item) = state.selected_task().cloned() {
+                    context.emit(self.pomodoro, PomodoroMessage::Start(item));
                 }
+                false
             }
-            KeyCode::Char('c') => state.creating_item = true,
-            KeyCode::Char('j') => {}
-            KeyCode::Char('k') => {}
-            KeyCode::Esc => {
-                state.creating_item = false;
-                state.buffer.clear();
+            Action::Plan => {
+                context.emit(self.planning, PlanningMessage::Toggle);
+                false
             }
-            KeyCode::Enter => {
-                if state.creating_item {
-                    let item = TaskItem::new(state.buffer.clone(), String::default(), false);
-                    state.list.push(item);
-                    state.selection.push(state.buffer.clone());
-                    state.buffer.clear();
-                    state.creating_item = false;
+            Action::PlanLater => state.shift_planned(1),
+            Action::PlanEarlier => state.shift_planned(-1),
+            Action::EstimateUp => state.grow_estimate(),
+            Action::EstimateDown => state.shrink_estimate(),
+            Action::Schedule => state.schedule(self.capacity),
+            Action::Remove => {
+                let removed = state.remove_selected();
+                if !removed {
+                    state.selected_item.set(String::default());
                 }
+                removed
+            }
+            Action::Create => {
+                state.creating_item = true;
+                false
+            }
+            Action::Sort => {
+                state.toggle_sort();
+                false
+            }
+            Action::Waiting => {
+                state.toggle_waiting();
+                false
             }
-            KeyCode::Char(ch) => {
-                if state.creating_item {
-                    state.buffer.push(ch)
+            Action::Snooze => state.snooze(),
+            Action::Wake => state.wake(),
+            Action::Repeat => state.cycle_repeat(),
+            Action::Save => {
+                self.save(state);
+                false
+            }
+            Action::Quit => {
+                if !state.is_unsaved() {
+                    self.quit();
                 }
+                state.ask(Some(Prompt::Quit));
+                false
             }
+        };
+        if changed {
+            self.announce(state, &mut context);
+        }
+        Self::keep_visible(state, &mut elements);
+    }
 
-            _ => {}
+    fn on_focus(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: Context<'_, Self::State>,
+    ) {
+        state.border_color.set(self.colors.get(true));
+    }
+
+    fn on_blur(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: Context<'_, Self::State>,
+    ) {
+        state.border_color.set(self.colors.get(false));
+    }
+
+    fn tick(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: Context<'_, Self::State>,
+        _dt: Duration,
+    ) {
+        if state.today != clock::today() {
+            let selected = state.selected_name();
+            state.refresh();
+            state.select_by_name(selected);
+        }
+        if state.list.running().is_some() {
+            state.update_timer();
+        }
+
+        if state.touched {
+            state.touched = false;
+            state.unsaved.set(state.is_unsaved());
+        }
+        let Some(delay) = self.autosave else {
+            return;
+        };
+        if state.pending.is_some() || state.last_input.is_none_or(|at| at.elapsed() < delay) {
+            return;
+        }
+        state.last_input = None;
+        if state.is_unsaved() {
+            self.save(state);
         }
     }
 
@@ -127,8 +982,14 @@ impl Component for TaskSelection {
         elements.by_tag("overflow").first(|el, _| {
             let overflow = el.to::<Overflow>();
             match mouse.state {
-                MouseState::ScrollUp => overflow.scroll_up_by(3),
-                MouseState::ScrollDown => overflow.scroll_down_by(3),
+                MouseState::ScrollUp => {
+                    overflow.scroll_up_by(3);
+                    state.offset = state.offset.saturating_sub(3);
+                }
+                MouseState::ScrollDown => {
+                    overflow.scroll_down_by(3);
+                    state.offset = (state.offset + 3).min(state.order.len().saturating_sub(1));
+                }
                 _ => {}
             }
         });
@@ -141,29 +1002,70 @@ impl Component for TaskSelection {
         let (x, y) = (pos.x as usize, pos.y as usize);
 
         let mut line: usize = 0;
-        for (i, task) in state.list.iter().enumerate() {
+        let rows = state
+            .order
+            .iter()
+            .filter_map(|index| state.list.get(*index));
+        for (i, task) in rows.enumerate() {
             // we want to skip the top border,
-            // we do i + 1 so that clicking on line 1 returns the first task
-            if y == i + 1 {
+            // we do i + 1 so that clicking on line 1 returns the first task,
+            // rows scrolled out of view are above line 1
+            if y + state.offset == i + 1 {
                 if state
                     .selected
                     .to_number()
                     .is_some_and(|n| n.as_uint() == line)
                 {
                     state.selected.set(None);
+                    context.emit(self.habits, HabitsMessage::Show(None));
                     break;
                 }
-                if let Some(task) = state.list.get(line) {
+                if let Some(task) = state
+                    .order
+                    .get(line)
+                    .and_then(|index| state.list.get(*index))
+                {
                     if x <= task.name().len() {
                         state.selected.set(Some(line));
-                        let item = task.to_string();
-                        state.selected_item.set(item);
+                        state.selected_item.set(task.name().to_string());
                     }
                 }
-                context.publish("selection_bar", |state| &state.selected_item);
+                self.announce(state, &mut context);
+                self.open(&mut context);
                 break;
             }
             line += task.name().lines().count();
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use tasks_core::tasks::{TaskItem, TaskList};
+
+    use super::TaskSelectionState;
+    use crate::config::Config;
+
+    #[test]
+    pub fn test_stale_edit_is_refused() {
+        let mut list = TaskList::new();
+        list.push(TaskItem::new("a".into(), "".into(), false));
+        list.push(TaskItem::new("b".into(), "".into(), false));
+        let mut state = TaskSelectionState::new(list, &Config::default());
+        state.select_row(Some(0));
+        let shown = state.list[0].clone();
+        assert!(state.remove_selected());
+
+        // the editor still shows `a` at index 0, where `b` is now
+        assert!(!state.edit_task(0, &shown, "a2".into(), true, "x".into()));
+        assert_eq!(state.list.len(), 1);
+        assert_eq!(state.list[0].name(), "b");
+        assert!(!state.list[0].status());
+        assert_eq!(state.list[0].data(), "");
+
+        let shown = state.list[0].clone();
+        assert!(state.edit_task(0, &shown, "b2".into(), true, "".into()));
+        assert_eq!(state.list[0].name(), "b2");
+        assert!(state.list[0].status());
+    }
+}
@@ -1,10 +1,15 @@
-use std::fmt::Display;
-
 use anathema::{
-    component::{Component, KeyCode},
+    component::{Component, ComponentId, KeyCode},
     state::{State, Value},
 };
-use tasks_core::tasks::{ParserState, TaskError};
+use tasks_core::tasks::TaskItem;
+
+use crate::{
+    config::Config,
+    focus::{self, FocusColors, Pane},
+    keys::Keymap,
+    selection::{same_task, SelectionMessage},
+};
 
 #[derive(Clone, Debug, PartialEq)]
 enum EditingState {
@@ -19,37 +24,98 @@ pub enum Direction {
     Right,
 }
 
+/// the byte offset of the `idx`th character in `text`, or its end
+fn byte_offset(text: &str, idx: usize) -> usize {
+    text.char_indices()
+        .nth(idx)
+        .map_or(text.len(), |(offset, _)| offset)
+}
+
 #[derive(Default, Debug, State)]
 pub struct TaskEditorState {
     is_selected: Value<bool>,
+    border_color: Value<String>,
     name: Value<String>,
     status: Value<bool>,
     data: Value<String>,
+    /// the cursor, counted in characters
     #[state_ignore]
     idx: usize,
     #[state_ignore]
     selected: Option<EditingState>,
+    /// the task being edited and its index in the list, as it was received
+    #[state_ignore]
+    task: Option<(usize, TaskItem)>,
 }
 
 impl TaskEditorState {
-    pub fn new(content: String) -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
-            idx: content.len(),
+            border_color: Value::new(config.theme.border.clone()),
             ..Default::default()
         }
     }
 
+    /// starts editing `field` with the cursor at its end
+    fn edit_field(&mut self, field: EditingState) {
+        self.idx = match field {
+            EditingState::Name => self.name.to_ref().chars().count(),
+            EditingState::Status => match self.status.to_ref().to_bool() {
+                true => 4,
+                false => 5,
+            },
+            EditingState::Data => self.data.to_ref().chars().count(),
+        };
+        self.selected = Some(field);
+    }
+
+    /// shows `task` or clears the editor. When it is the task already being
+    /// edited, e.g. because the list was saved or reloaded, the field being
+    /// typed in is kept and only the others are refreshed.
+    fn show(&mut self, task: Option<(usize, TaskItem)>) {
+        let same = match (&self.task, &task) {
+            (Some((_, old)), Some((_, new))) => same_task(old, new),
+            _ => false,
+        };
+        self.task = task;
+        match same {
+            true => self.load(self.selected.clone()),
+            false => self.revert(),
+        }
+    }
+
+    /// fills the fields from the task, except the one being edited
+    fn load(&mut self, editing: Option<EditingState>) {
+        self.is_selected.set(self.task.is_some());
+        let Some((_, item)) = &self.task else {
+            return;
+        };
+        if editing != Some(EditingState::Name) {
+            self.name.set(item.name().to_string());
+        }
+        if editing != Some(EditingState::Status) {
+            self.status.set(item.status());
+        }
+        if editing != Some(EditingState::Data) {
+            self.data.set(item.data().to_string());
+        }
+    }
+
     pub fn push(&mut self, idx: usize, ch: char) {
         let Some(ref state) = self.selected else {
             return;
         };
         match state {
             EditingState::Name => {
-                self.name.to_mut().insert(idx, ch);
+                let mut name = self.name.to_mut();
+                let at = byte_offset(&name, idx);
+                name.insert(at, ch);
                 self.idx += 1;
             }
             EditingState::Data => {
-                self.data.to_mut().insert(idx, ch);
+                let mut data = self.data.to_mut();
+                let at = byte_offset(&data, idx);
+                data.insert(at, ch);
                 self.idx += 1;
             }
             _ => {}
@@ -65,11 +131,19 @@ impl TaskEditorState {
         };
         match state {
             EditingState::Name => {
-                self.name.to_mut().remove(idx);
+                let mut name = self.name.to_mut();
+                let at = byte_offset(&name, idx);
+                if at < name.len() {
+                    name.remove(at);
+                }
                 self.idx -= 1;
             }
             EditingState::Data => {
-                self.data.to_mut().remove(idx);
+                let mut data = self.data.to_mut();
+                let at = byte_offset(&data, idx);
+                if at < data.len() {
+                    data.remove(at);
+                }
                 self.idx -= 1;
             }
             _ => {}
@@ -91,12 +165,12 @@ impl TaskEditorState {
             },
             Direction::Right => match state {
                 EditingState::Name => {
-                    if self.idx < self.name.to_ref().len().saturating_sub(1) {
+                    if self.idx < self.name.to_ref().chars().count() {
                         self.idx += 1;
                     }
                 }
                 EditingState::Data => {
-                    if self.idx < self.data.to_ref().len().saturating_sub(1) {
+                    if self.idx < self.data.to_ref().chars().count() {
                         self.idx += 1;
                     }
                 }
@@ -106,59 +180,62 @@ impl TaskEditorState {
     }
 
     pub fn toggle_status(&mut self) {
-        self.status.set(self.status.to_bool());
+        let status = *self.status.to_ref();
+        self.status.set(!status);
     }
 
-    fn parse(&mut self, content: String) -> Result<TaskEditorState, TaskError> {
-        let mut editor = TaskEditorState::default();
-        let mut state = ParserState::Name;
-        let mut data = String::default();
+    /// stops editing the current field and throws away what was typed
+    fn revert(&mut self) {
+        self.selected = None;
+        self.load(None);
+    }
 
-        for (i, line) in content.lines().enumerate() {
-            match state {
-                ParserState::Name => {
-                    editor.name.set(line.trim().to_string());
-                    state = ParserState::Status;
-                }
-                ParserState::Status => {
-                    if line.eq("true") {
-                        editor.status.set(true);
-                    } else if line.eq("false") {
-                        editor.status.set(false);
-                    } else {
-                        return Err(TaskError::ParseError("Incorrect status".to_string()));
-                    }
-                    state = ParserState::Data;
-                }
-                ParserState::Data => {
-                    data.push_str(line.trim());
-                    editor.data.set(line.trim().to_string());
-                    tracing::info!("{i}");
-                }
-            }
-        }
-        editor.data.set(data);
-        Ok(editor)
+    /// stops editing the current field and returns the fields the editor
+    /// shows, the list applies them to its own copy of the task so nothing
+    /// else it changed since is lost
+    fn commit(&mut self) -> Option<SelectionMessage> {
+        self.selected = None;
+        let (index, task) = self.task.as_ref()?;
+        Some(SelectionMessage::Edit {
+            index: *index,
+            task: task.clone(),
+            name: self.name.to_ref().to_string(),
+            status: *self.status.to_ref(),
+            data: self.data.to_ref().to_string(),
+        })
     }
 }
 
-#[derive(Default)]
-pub struct TaskEditor;
+pub enum EditorMessage {
+    /// where edits are sent, the list is registered after the editor so it
+    /// can't be passed in when the editor is created
+    Connect(ComponentId<SelectionMessage>),
+    /// the selected task and its index in the list
+    Show(Option<(usize, TaskItem)>),
+}
+
+/// Edits the selected task. A field is picked with the mouse or with `n`,
+/// `s` and `d`, changes are sent back to the list when Enter is pressed and
+/// Esc throws them away.
+pub struct TaskEditor {
+    selection: Option<ComponentId<SelectionMessage>>,
+    keys: Keymap,
+    colors: FocusColors,
+}
 
-impl Display for TaskEditorState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!(
-            "{:?} {:?} {:?}",
-            self.name.to_ref(),
-            self.status.to_ref(),
-            self.data.to_ref()
-        ))
+impl TaskEditor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            selection: None,
+            keys: config.keys.clone(),
+            colors: FocusColors::new(&config.theme),
+        }
     }
 }
 
 impl Component for TaskEditor {
     type State = TaskEditorState;
-    type Message = String;
+    type Message = EditorMessage;
 
     fn on_mouse(
         &mut self,
@@ -176,21 +253,15 @@ impl Component for TaskEditor {
         let data_start = 8..10;
         elements.by_tag("border").each(|_, _| {
             if name_start.contains(&mouse.y) {
-                state.selected = Some(EditingState::Name);
-                state.idx = state.name.to_ref().len();
+                state.edit_field(EditingState::Name);
                 tracing::info!("EDITING NAME");
             }
             if status_start.contains(&mouse.y) {
-                state.selected = Some(EditingState::Status);
-                state.idx = match state.status.to_ref().to_bool() {
-                    true => 4,
-                    false => 5,
-                };
+                state.edit_field(EditingState::Status);
                 tracing::info!("EDITING STATUS");
             }
             if data_start.contains(&mouse.y) {
-                state.selected = Some(EditingState::Data);
-                state.idx = state.data.to_ref().len();
+                state.edit_field(EditingState::Data);
                 tracing::info!("EDITING DATA");
             }
             //tracing::info!("el: {el:?}")
@@ -202,11 +273,37 @@ impl Component for TaskEditor {
         key: anathema::component::KeyEvent,
         state: &mut Self::State,
         _elements: anathema::widgets::Elements<'_, '_>,
-        _context: anathema::prelude::Context<'_, Self::State>,
+        mut context: anathema::prelude::Context<'_, Self::State>,
     ) {
+        // an edit in progress is kept when focus moves away
+        if focus::cycle(&self.keys, &key, Pane::Editor, &mut context) {
+            return;
+        }
         if state.selected.is_none() {
+            if !*state.is_selected.to_ref() {
+                return;
+            }
+            match key.code {
+                KeyCode::Char('n') | KeyCode::Enter => state.edit_field(EditingState::Name),
+                KeyCode::Char('s') => state.edit_field(EditingState::Status),
+                KeyCode::Char('d') => state.edit_field(EditingState::Data),
+                _ => {}
+            }
             return;
         }
+        match key.code {
+            KeyCode::Enter => {
+                if let (Some(selection), Some(edit)) = (self.selection, state.commit()) {
+                    context.emit(selection, edit);
+                }
+                return;
+            }
+            KeyCode::Esc => {
+                state.revert();
+                return;
+            }
+            _ => {}
+        }
         if let Some(selected) = &state.selected {
             match selected {
                 EditingState::Status => {
@@ -223,65 +320,36 @@ impl Component for TaskEditor {
                 },
             }
         }
-        //match key.code {
-        //    KeyCode::Char('t') => state.toggle_status(),
-        //    KeyCode::Char(c) => {
-        //        state.push(c);
-        //        state.idx += 1;
-        //    }
-        //    KeyCode::Backspace => {
-        //        if state.idx > 0 {
-        //            state.remove(state.idx);
-        //            state.idx -= 1;
-        //        }
-        //    }
-        //    KeyCode::Left => state.idx -= 1,
-        //    KeyCode::Right => state.idx += 1,
-        //    _ => {}
-        //}
     }
 
-    fn message(
+    fn on_focus(
         &mut self,
-        message: Self::Message,
         state: &mut Self::State,
         _elements: anathema::widgets::Elements<'_, '_>,
         _context: anathema::prelude::Context<'_, Self::State>,
     ) {
-        if let Ok(item) = state.parse(message.clone()) {
-            state.is_selected.set(true);
-            state.name.set(item.name.to_ref().to_string());
-            state.status.set(*item.status.to_ref());
-
-            let str = item.data.to_ref().to_string();
-
-            tracing::info!("out editor {str:?}");
-
-            let mut nl = (false, false);
+        state.border_color.set(self.colors.get(true));
+    }
 
-            let data = str
-                .chars()
-                .map(|c| match c {
-                    '\\' => {
-                        nl.0 = true;
-                        ' '
-                    }
-                    'n' => {
-                        if nl.0 {
-                            nl.1 = true;
-                            '\n'
-                        } else {
-                            'n'
-                        }
-                    }
-                    _ => c,
-                })
-                .collect();
+    fn on_blur(
+        &mut self,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        state.border_color.set(self.colors.get(false));
+    }
 
-            tracing::info!("in editor {str:?}");
-            state.data.set(data);
-        } else {
-            tracing::info!("failed to parse");
+    fn message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _elements: anathema::widgets::Elements<'_, '_>,
+        _context: anathema::prelude::Context<'_, Self::State>,
+    ) {
+        match message {
+            EditorMessage::Connect(selection) => self.selection = Some(selection),
+            EditorMessage::Show(task) => state.show(task),
         }
     }
 }
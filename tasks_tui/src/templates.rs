@@ -0,0 +1,250 @@
+//! The component templates are compiled into the binary. A file with the
+//! same name in `$XDG_CONFIG_HOME/tasks/templates/` replaces the built-in one.
+//!
+//! The runtime loads copies kept in `$XDG_STATE_HOME/tasks/templates/` and
+//! reloads them whenever they change. An override is compiled before it is
+//! copied there, so a broken edit is reported while the last working version
+//! stays on screen.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+use anathema::templates::{Document, SourceKind};
+use notify::{RecursiveMode, Watcher};
+
+/// component name, file name and the built-in source
+const BUILTIN: [(&str, &str, &str); 7] = [
+    ("main", "main.aml", include_str!("../../templates/main.aml")),
+    (
+        "selection",
+        "list.aml",
+        include_str!("../../templates/list.aml"),
+    ),
+    (
+        "editor",
+        "task_editor.aml",
+        include_str!("../../templates/task_editor.aml"),
+    ),
+    (
+        "navbar",
+        "navbar.aml",
+        include_str!("../../templates/navbar.aml"),
+    ),
+    (
+        "habits",
+        "habits.aml",
+        include_str!("../../templates/habits.aml"),
+    ),
+    (
+        "planning",
+        "planning.aml",
+        include_str!("../../templates/planning.aml"),
+    ),
+    (
+        "pomodoro",
+        "pomodoro.aml",
+        include_str!("../../templates/pomodoro.aml"),
+    ),
+];
+
+/// how long the override directory has to be quiet before it is reloaded
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// the templates touched by a file system event
+fn changed_templates(event: notify::Result<notify::Event>) -> BTreeSet<&'static str> {
+    let Ok(event) = event else {
+        return BTreeSet::new();
+    };
+    if !(event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove()) {
+        return BTreeSet::new();
+    }
+    BUILTIN
+        .iter()
+        .map(|(_, name, _)| *name)
+        .filter(|name| {
+            event
+                .paths
+                .iter()
+                .any(|path| path.file_name().is_some_and(|file| file == *name))
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct TemplateError {
+    path: PathBuf,
+    message: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// compiles `source` on its own, the components it uses are stubbed out
+fn check(source: &str) -> Result<(), String> {
+    let mut document = Document::new(source.to_string());
+    for (component, _, _) in BUILTIN {
+        document
+            .add_component(component, SourceKind::Str(String::new()))
+            .map_err(|e| e.to_string())?;
+    }
+    document.compile().map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Templates {
+    /// where overrides are read from
+    overrides: Option<PathBuf>,
+    /// the copies the runtime loads
+    live: PathBuf,
+    /// overrides that failed to load by file name
+    errors: BTreeMap<&'static str, String>,
+}
+
+impl Templates {
+    /// writes the built-in templates to `live`, replacing each one that has
+    /// a working override in `overrides`
+    pub fn load(overrides: Option<PathBuf>, live: PathBuf) -> Result<Self, TemplateError> {
+        let io_err = |path: &Path, e: std::io::Error| TemplateError {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        };
+        std::fs::create_dir_all(&live).map_err(|e| io_err(&live, e))?;
+        for (_, name, source) in BUILTIN {
+            let path = live.join(name);
+            std::fs::write(&path, source).map_err(|e| io_err(&path, e))?;
+        }
+
+        let mut templates = Self {
+            overrides,
+            live,
+            errors: BTreeMap::new(),
+        };
+        for (_, name, _) in BUILTIN {
+            templates.update(name)?;
+        }
+        Ok(templates)
+    }
+
+    /// re-reads the override for `name` and hands it to the runtime if it
+    /// compiles, without an override the built-in template is used
+    fn update(&mut self, name: &'static str) -> Result<(), TemplateError> {
+        let builtin = BUILTIN
+            .iter()
+            .find_map(|(_, file, source)| (*file == name).then_some(*source))
+            .unwrap_or_default();
+        let source = match &self.overrides {
+            None => builtin.to_string(),
+            Some(dir) => {
+                let path = dir.join(name);
+                let result = match std::fs::read_to_string(&path) {
+                    Ok(source) => check(&source).map(|_| source),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(builtin.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                match result {
+                    Ok(source) => source,
+                    Err(message) => {
+                        self.errors
+                            .insert(name, format!("{}: {message}", path.display()));
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        self.errors.remove(name);
+
+        let path = self.live.join(name);
+        // rewriting an unchanged file would still make the runtime reload it
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(source.as_str()) {
+            std::fs::write(&path, source).map_err(|e| TemplateError {
+                path,
+                message: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// the file the runtime should load for `name`, e.g. `list.aml`
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.live.join(name)
+    }
+
+    /// every override that failed to load, one per line
+    pub fn errors(&self) -> String {
+        self.errors.values().cloned().collect::<Vec<_>>().join("\n")
+    }
+
+    /// watches the override directory and reloads templates as they are
+    /// edited, the receiver gets the new `errors` after every change
+    pub fn watch(&self) -> Result<Receiver<String>, TemplateError> {
+        let mut templates = self.clone();
+        let Some(dir) = templates.overrides.clone() else {
+            return Ok(channel().1);
+        };
+        let err = |e: String| TemplateError {
+            path: dir.clone(),
+            message: e,
+        };
+        std::fs::create_dir_all(&dir).map_err(|e| err(e.to_string()))?;
+
+        let (events_tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(events_tx).map_err(|e| err(e.to_string()))?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(|e| err(e.to_string()))?;
+
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            // keep the watcher alive for as long as the thread runs
+            let _watcher = watcher;
+            while let Ok(event) = events.recv() {
+                // editors write a file in several steps, wait for them to
+                // finish so a half written template isn't picked up
+                let mut changed = changed_templates(event);
+                while let Ok(event) = events.recv_timeout(SETTLE) {
+                    changed.extend(changed_templates(event));
+                }
+                if changed.is_empty() {
+                    continue;
+                }
+                for name in changed {
+                    if let Err(e) = templates.update(name) {
+                        tracing::error!("failed to reload {name}: {e}");
+                    }
+                }
+                if tx.send(templates.errors()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    /// explains a failure to compile the templates, pointing at the
+    /// overrides since the built-in templates are known to compile
+    pub fn compile_error(&self, err: impl Display) -> String {
+        let overrides: Vec<String> = self
+            .overrides
+            .iter()
+            .flat_map(|dir| BUILTIN.iter().map(move |(_, name, _)| dir.join(name)))
+            .filter(|path| path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if overrides.is_empty() {
+            return format!("failed to compile the templates: {err}");
+        }
+        format!(
+            "failed to compile the templates: {err}\n\
+            the following overrides are in use, fix or remove them to fall back to the built-in templates:\n  {}",
+            overrides.join("\n  ")
+        )
+    }
+}